@@ -1,8 +1,7 @@
-use clap::{Args, Parser, Subcommand};
-use itertools::Itertools;
-use std::collections::HashSet;
-use winreg_common::root::RootKey;
-use winreg_export::ExportKey;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use winreg_common::hive::ParseOptions;
+use winreg_export::ExportFormat;
+use winreg_interrogate::query::{Query, QueryError};
 /// Program used to export or interrogate registry hive files
 #[derive(Parser, Clone, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -19,6 +18,8 @@ pub enum Commands {
     Export(ExportArgs),
     /// Interrogate a registry hive with various predicates
     Interrogate(InterrogateArgs),
+    /// Print the header fields of registry hive files
+    Info(InfoArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -29,13 +30,133 @@ pub struct ExportArgs {
     /// The keys to export
     #[arg(short, long)]
     keys: Vec<String>,
+    /// Export from this hive file instead of the live registry
+    #[arg(short, long)]
+    input_hive: Option<String>,
+    /// The format to export keys from a hive file to
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value_t = ExportFormatArg::Reg,
+        requires = "input_hive"
+    )]
+    format: ExportFormatArg,
+    /// Where the hive file is loaded in the registry, e.g. HKLM\SOFTWARE. Detected from the
+    /// kind of hive when not given
+    #[arg(short, long, requires = "input_hive")]
+    mount_point: Option<String>,
+    /// Recover a dirty hive file from the .LOG1 and .LOG2 transaction logs next to it
+    #[arg(long, requires = "input_hive")]
+    replay_logs: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ExportFormatArg {
+    /// A .reg file as written by regedit
+    Reg,
+    /// A JSON document of nested keys
+    Json,
+    /// Newline delimited JSON with one key per line
+    Ndjson,
+    /// Comma-separated values with one row per value
+    Csv,
+}
+
+impl From<ExportFormatArg> for ExportFormat {
+    fn from(format: ExportFormatArg) -> Self {
+        match format {
+            ExportFormatArg::Reg => ExportFormat::Reg,
+            ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Ndjson => ExportFormat::Ndjson,
+            ExportFormatArg::Csv => ExportFormat::Csv,
+        }
+    }
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct InterrogateArgs {
-    /// The input hive files to interrogate
+    /// The input hive files to interrogate. Files with a .reg extension are read as .reg files
     #[arg(short, long)]
-    input_hive: Vec<String>
+    input_hive: Vec<String>,
+    /// List the keys and values recovered from unallocated cells, filtered by the other predicates
+    #[arg(long)]
+    recover_deleted: bool,
+    /// Recover dirty hives from the .LOG1 and .LOG2 transaction logs next to them
+    #[arg(long)]
+    replay_logs: bool,
+    /// Only report keys whose path relative to the root key matches this glob pattern, e.g.
+    /// ControlSet*\Services\*\Parameters
+    #[arg(long)]
+    key_path: Option<String>,
+    /// Only report values whose name matches this regular expression
+    #[arg(long)]
+    value_name: Option<String>,
+    /// Only report values of these types, e.g. REG_SZ or dword
+    #[arg(long)]
+    value_type: Vec<String>,
+    /// Only report values whose data contains this text, ignoring case
+    #[arg(long)]
+    data_contains: Option<String>,
+    /// Only report values whose data matches this regular expression
+    #[arg(long)]
+    data_regex: Option<String>,
+    /// Only report keys last written at or after this time, e.g. 2023-01-02T03:04:05Z
+    #[arg(long)]
+    written_after: Option<String>,
+    /// Only report keys last written before this time
+    #[arg(long)]
+    written_before: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct InfoArgs {
+    /// The input hive files to describe
+    #[arg(short, long)]
+    input_hive: Vec<String>,
+}
+
+impl InfoArgs {
+    pub fn get_input_hives(&self) -> &[String] {
+        &self.input_hive
+    }
+}
+
+impl InterrogateArgs {
+    pub fn get_input_hives(&self) -> &[String] {
+        &self.input_hive
+    }
+
+    pub fn recover_deleted(&self) -> bool {
+        self.recover_deleted
+    }
+
+    pub fn build_parse_options(&self) -> ParseOptions {
+        ParseOptions::new().with_replay_logs(self.replay_logs)
+    }
+
+    pub fn build_query(&self) -> Result<Query, QueryError> {
+        let mut query = Query::new().with_value_types(&self.value_type)?;
+        if let Some(key_path) = &self.key_path {
+            query = query.with_key_path(key_path)?;
+        }
+        if let Some(value_name) = &self.value_name {
+            query = query.with_value_name(value_name)?;
+        }
+        if let Some(data_contains) = &self.data_contains {
+            query = query.with_data_contains(data_contains);
+        }
+        if let Some(data_regex) = &self.data_regex {
+            query = query.with_data_regex(data_regex)?;
+        }
+        if let Some(written_after) = &self.written_after {
+            query = query.with_written_after(written_after)?;
+        }
+        if let Some(written_before) = &self.written_before {
+            query = query.with_written_before(written_before)?;
+        }
+        Ok(query)
+    }
 }
 
 impl ExportArgs {
@@ -43,32 +164,23 @@ impl ExportArgs {
         &self.output_path
     }
 
-    pub fn build_export_keys(&self) -> Vec<ExportKey> {
-        let keys: Vec<ExportKey> = self
-            .keys
-            .iter()
-            .map(|key| key.to_uppercase().replace("\\\\", "\\"))
-            .filter_map(|key| {
-                return match RootKey::try_from(key.as_str()) {
-                    Ok(root) => {
-                        let sub_key = key[key.find('\\').unwrap() + 1..].to_string();
-                        Some((root, sub_key))
-                    }
-                    Err(e) => {
-                        eprintln!("Failed parsing key: {}", e.msg());
-                        None
-                    }
-                };
-            })
-            .into_group_map()
-            .into_iter()
-            .map(|grouped| {
-                ExportKey::new(
-                    grouped.0,
-                    HashSet::from_iter(grouped.1.into_iter().collect::<Vec<String>>()),
-                )
-            })
-            .collect();
-        keys
+    pub fn get_keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn get_input_hive(&self) -> Option<&str> {
+        self.input_hive.as_deref()
+    }
+
+    pub fn get_format(&self) -> ExportFormat {
+        self.format.into()
+    }
+
+    pub fn get_mount_point(&self) -> Option<&str> {
+        self.mount_point.as_deref()
+    }
+
+    pub fn build_parse_options(&self) -> ParseOptions {
+        ParseOptions::new().with_replay_logs(self.replay_logs)
     }
 }
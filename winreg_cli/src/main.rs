@@ -1,32 +1,76 @@
 use clap::Parser;
-use std::path::PathBuf;
+use rayon::ThreadPool;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
-use winreg_cli::{Cli, Commands, ExportArgs, InterrogateArgs};
-use winreg_export::export;
+use winreg_cli::{Cli, Commands, ExportArgs, InfoArgs, InterrogateArgs};
+use winreg_export::backend::{ExportBackend, OfflineBackend, RegSaveBackend};
+use winreg_export::{export, ExportError};
 
 fn main() -> io::Result<()> {
     let args = Cli::parse();
     match args.command {
         Commands::Export(args) => run_export(args),
         Commands::Interrogate(args) => run_interrogate(args),
+        Commands::Info(args) => run_info(args),
     }
 }
 
 fn run_export(args: ExportArgs) -> io::Result<()> {
     let export_path = PathBuf::from(args.get_output_path());
-    let export_keys = args.build_export_keys();
 
     if export_path.is_dir() {
         eprintln!("The directory {} already exists", &export_path.display());
         return Ok(());
     }
 
-    fs::create_dir_all(&export_path).unwrap_or_else(|_| panic!("Failed creating export directory: {}",
-        &export_path.display()));
+    let backend: Result<Box<dyn ExportBackend>, ExportError> = match args.get_input_hive() {
+        Some(input_hive) => {
+            let Some(pool) = build_thread_pool() else {
+                return Ok(());
+            };
+            OfflineBackend::open(
+                Path::new(input_hive),
+                &pool,
+                &args.build_parse_options(),
+                args.get_mount_point(),
+                args.get_format(),
+            )
+            .map(|backend| {
+                if let Some(error) = backend.hive().checksum_error() {
+                    eprintln!("Warning: {}: {}", input_hive, error);
+                }
+                for (log_path, error) in backend.hive().skipped_logs() {
+                    eprintln!("Warning: skipped {}: {}", log_path.display(), error);
+                }
+                Box::new(backend) as Box<dyn ExportBackend>
+            })
+        }
+        None => RegSaveBackend::new().map(|backend| Box::new(backend) as Box<dyn ExportBackend>),
+    };
+    let mut backend = match backend {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("Error exporting registry keys: {}", e.msg());
+            return Ok(());
+        }
+    };
+
+    fs::create_dir_all(&export_path).unwrap_or_else(|_| {
+        panic!(
+            "Failed creating export directory: {}",
+            &export_path.display()
+        )
+    });
 
     println!("Exporting registry keys to {}", &export_path.display());
 
-    match export(export_keys, export_path) {
+    // A hive file is exported as a whole when no keys are given
+    let keys = if args.get_keys().is_empty() && args.get_input_hive().is_some() {
+        vec![String::new()]
+    } else {
+        args.get_keys().to_vec()
+    };
+    match export(backend.as_mut(), &keys, &export_path) {
         Ok(_) => {
             println!("Export completed")
         }
@@ -38,7 +82,53 @@ fn run_export(args: ExportArgs) -> io::Result<()> {
     Ok(())
 }
 
-fn run_interrogate(_args: InterrogateArgs) -> io::Result<()> {
-    winreg_interrogate::test();
+fn run_interrogate(args: InterrogateArgs) -> io::Result<()> {
+    let query = match args.build_query() {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("Failed parsing query: {}", e.msg());
+            return Ok(());
+        }
+    };
+    let Some(pool) = build_thread_pool() else {
+        return Ok(());
+    };
+    if args.recover_deleted() {
+        winreg_interrogate::print_deleted(
+            args.get_input_hives(),
+            &query,
+            &pool,
+            &args.build_parse_options(),
+        );
+    } else {
+        winreg_interrogate::query::print_matches(
+            args.get_input_hives(),
+            &query,
+            &pool,
+            &args.build_parse_options(),
+        );
+    }
     Ok(())
 }
+
+fn run_info(args: InfoArgs) -> io::Result<()> {
+    winreg_interrogate::info::print_info(args.get_input_hives());
+    Ok(())
+}
+
+/// Builds a thread pool for parsing hives with a thread per available core, or a single thread
+/// when the number of cores can not be determined. Prints the error when the pool can not be
+/// built.
+fn build_thread_pool() -> Option<ThreadPool> {
+    let num_threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+    {
+        Ok(pool) => Some(pool),
+        Err(e) => {
+            eprintln!("Failed building the thread pool: {}", e);
+            None
+        }
+    }
+}
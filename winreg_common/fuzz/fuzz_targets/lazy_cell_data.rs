@@ -0,0 +1,29 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use rayon::ThreadPool;
+use std::sync::OnceLock;
+use winreg_common::hive::{parse_registry_with_options, Hive, ParseMode, ParseOptions};
+
+fn pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| rayon::ThreadPoolBuilder::new().build().unwrap())
+}
+
+fuzz_target!(|input: (Vec<u8>, Vec<u32>)| {
+    let (data, offsets) = input;
+    let options = ParseOptions::new().with_parse_mode(ParseMode::Lazy);
+    let Ok(primary_file) = parse_registry_with_options(Bytes::from(data), pool(), &options) else {
+        return;
+    };
+    // Lazy mode decodes cells on demand, so any offset may reach the decoding code
+    for offset in offsets {
+        let _ = primary_file.cell_data(offset);
+    }
+    let hive = Hive::new(primary_file);
+    if let Ok(root) = hive.root() {
+        let _ = root.values();
+        let _ = root.subkeys();
+    }
+});
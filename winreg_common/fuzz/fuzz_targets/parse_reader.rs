@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashSet;
+use std::io::Cursor;
+use winreg_common::hive::Hive;
+
+fuzz_target!(|data: &[u8]| {
+    // Reading through a stream goes through the page cache instead of an in-memory copy
+    let Ok(hive) = Hive::from_reader(Cursor::new(data.to_vec())) else {
+        return;
+    };
+    let Ok(root) = hive.root() else {
+        return;
+    };
+
+    // Walk every reachable key, guarding against cycles in the subkey lists
+    let mut visited = HashSet::new();
+    let mut keys = vec![root];
+    while let Some(key) = keys.pop() {
+        if !visited.insert(key.offset()) {
+            continue;
+        }
+        let _ = key.parent();
+        let _ = key.class_name();
+        let _ = hive.open_key(&key.path());
+        if let Ok(values) = key.values() {
+            for value in values {
+                let _ = value.reg_value().map(|reg_value| reg_value.to_string());
+            }
+        }
+        if let Ok(subkeys) = key.subkeys() {
+            keys.extend(subkeys);
+        }
+    }
+});
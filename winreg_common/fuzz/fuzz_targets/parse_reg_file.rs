@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use winreg_common::regfile::RegFile;
+use winreg_common::tree::KeyNode;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(reg_file) = RegFile::parse(data) else {
+        return;
+    };
+    let mut keys: Vec<&KeyNode> = vec![reg_file.root()];
+    while let Some(key) = keys.pop() {
+        for value in key.values() {
+            let _ = value.reg_value().to_string();
+        }
+        keys.extend(key.subkeys());
+    }
+});
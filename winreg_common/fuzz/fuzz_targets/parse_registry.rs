@@ -0,0 +1,45 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use rayon::ThreadPool;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use winreg_common::deleted::recover_deleted;
+use winreg_common::hive::{parse_registry, Hive};
+
+fn pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| rayon::ThreadPoolBuilder::new().build().unwrap())
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(primary_file) = parse_registry(Bytes::copy_from_slice(data), pool()) else {
+        return;
+    };
+    let hive = Hive::new(primary_file);
+    let Ok(root) = hive.root() else {
+        return;
+    };
+
+    // Walk every reachable key, guarding against cycles in the subkey lists
+    let mut visited = HashSet::new();
+    let mut keys = vec![root];
+    while let Some(key) = keys.pop() {
+        if !visited.insert(key.offset()) {
+            continue;
+        }
+        let _ = key.parent();
+        let _ = key.security().map(|security| security.sddl());
+        let _ = hive.open_key(&key.path());
+        if let Ok(values) = key.values() {
+            for value in values {
+                let _ = value.reg_value().map(|reg_value| reg_value.to_string());
+            }
+        }
+        if let Ok(subkeys) = key.subkeys() {
+            keys.extend(subkeys);
+        }
+    }
+    let _ = recover_deleted(&hive);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use winreg_common::hive::RegValue;
+
+fuzz_target!(|input: (u32, Vec<u8>)| {
+    let (data_type, data) = input;
+    let _ = RegValue::decode(data_type, Bytes::from(data)).to_string();
+});
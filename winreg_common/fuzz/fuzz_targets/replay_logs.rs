@@ -0,0 +1,26 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use rayon::ThreadPool;
+use std::sync::OnceLock;
+use winreg_common::hive::{parse_registry, Hive};
+use winreg_common::recovery::replay;
+
+fn pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| rayon::ThreadPoolBuilder::new().build().unwrap())
+}
+
+fuzz_target!(|input: (Vec<u8>, Vec<Vec<u8>>)| {
+    let (primary, logs) = input;
+    let logs: Vec<Bytes> = logs.into_iter().map(Bytes::from).collect();
+    let Ok(replayed) = replay(Bytes::from(primary), &logs) else {
+        return;
+    };
+    // The replayed hive must still be safe to parse, whatever the logs wrote into it
+    let Ok(primary_file) = parse_registry(replayed.into_hive(), pool()) else {
+        return;
+    };
+    let _ = Hive::new(primary_file).root();
+});
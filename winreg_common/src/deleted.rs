@@ -0,0 +1,350 @@
+use crate::hive::{CellData, Hive, HivePrimaryFile, NamedKey, RegValue, ValueKey};
+use crate::hive::{
+    CELL_SIG_SIZE, CELL_SIZE_LENGTH, HIVE_HEADER_SIZE, MAX_KEY_DEPTH, NAMED_KEY_SIG,
+    NAMED_KEY_SIZE, VALUE_KEY_SIG, VALUE_KEY_SIZE,
+};
+use crate::timestamp::FileTime;
+use bytes::{Buf, Bytes};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+const CELL_ALIGNMENT: usize = 8;
+const OFFSET_LIST_ELEMENT_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct RecoverDeletedError {
+    msg: String,
+}
+
+impl RecoverDeletedError {
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+impl fmt::Display for RecoverDeletedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for RecoverDeletedError {}
+
+/// A key recovered from an unallocated cell
+#[derive(Debug)]
+pub struct RecoveredKey {
+    offset: u32,
+    named_key: NamedKey,
+    path: Option<String>,
+    values: Vec<RecoveredValue>,
+}
+
+/// A value recovered from an unallocated cell
+#[derive(Debug)]
+pub struct RecoveredValue {
+    offset: u32,
+    value_key: ValueKey,
+    data: Option<RegValue>,
+}
+
+/// The keys and values found in the unallocated cells of a hive. Values which are not referenced
+/// by the value list of a recovered key are kept separately as orphans.
+#[derive(Debug, Default)]
+pub struct DeletedEntries {
+    keys: Vec<RecoveredKey>,
+    orphan_values: Vec<RecoveredValue>,
+}
+
+impl RecoveredKey {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn named_key(&self) -> &NamedKey {
+        &self.named_key
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        self.named_key.key_name()
+    }
+
+    pub fn last_written_timestamp(&self) -> FileTime {
+        self.named_key.last_written_timestamp()
+    }
+
+    /// Returns the path of the key relative to the root key, or `None` when the chain of parent
+    /// keys is broken because one of them was overwritten
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn values(&self) -> &[RecoveredValue] {
+        &self.values
+    }
+}
+
+impl RecoveredValue {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn value_key(&self) -> &ValueKey {
+        &self.value_key
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        self.value_key.value_name()
+    }
+
+    pub fn data_type(&self) -> u32 {
+        self.value_key.data_type()
+    }
+
+    /// Returns the decoded data, or `None` when the data cell has since been reallocated
+    pub fn data(&self) -> Option<&RegValue> {
+        self.data.as_ref()
+    }
+}
+
+impl DeletedEntries {
+    pub fn keys(&self) -> &[RecoveredKey] {
+        &self.keys
+    }
+
+    pub fn orphan_values(&self) -> &[RecoveredValue] {
+        &self.orphan_values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.orphan_values.is_empty()
+    }
+}
+
+/// Scans the unallocated cells of the hive for intact nk and vk structures and reconstructs the
+/// deleted keys and values they describe.
+/// Freed cells keep their contents until they are reused, and adjacent free cells are merged, so
+/// every free cell is searched for embedded cells at each 8-byte boundary.
+/// Hives read from a stream can not be scanned, as their bins are never read as a whole.
+pub fn recover_deleted(hive: &Hive) -> Result<DeletedEntries, RecoverDeletedError> {
+    let primary_file = hive.primary_file();
+    let data = primary_file
+        .hive_bins_data()
+        .ok_or_else(|| RecoverDeletedError {
+            msg: "Deleted entries can not be recovered from hives read from a stream".to_string(),
+        })?;
+    let mut named_keys = HashMap::new();
+    let mut value_keys = HashMap::new();
+    for (offset, cell_data) in free_cell_structures(primary_file, data) {
+        match cell_data {
+            CellData::NamedKey(named_key) => {
+                named_keys.insert(offset, named_key);
+            }
+            CellData::ValueKey(value_key) => {
+                value_keys.insert(offset, value_key);
+            }
+            _ => {}
+        }
+    }
+
+    let paths: HashMap<u32, Option<String>> = named_keys
+        .keys()
+        .map(|offset| (*offset, key_path(hive, &named_keys, *offset)))
+        .collect();
+    let mut named_keys: Vec<(u32, NamedKey)> = named_keys.into_iter().collect();
+    named_keys.sort_unstable_by_key(|(offset, _)| *offset);
+    let keys = named_keys
+        .into_iter()
+        .map(|(offset, named_key)| {
+            // Each value is attached to the first key referencing it, the rest become orphans
+            let values = value_list(primary_file, &named_key)
+                .into_iter()
+                .filter_map(|value_offset| {
+                    let value_key = value_keys.remove(&value_offset)?;
+                    Some(recover_value(hive, value_offset, value_key))
+                })
+                .collect();
+            RecoveredKey {
+                offset,
+                named_key,
+                path: paths[&offset].clone(),
+                values,
+            }
+        })
+        .collect();
+
+    let mut value_keys: Vec<(u32, ValueKey)> = value_keys.into_iter().collect();
+    value_keys.sort_unstable_by_key(|(offset, _)| *offset);
+    let orphan_values = value_keys
+        .into_iter()
+        .map(|(offset, value_key)| recover_value(hive, offset, value_key))
+        .collect();
+
+    Ok(DeletedEntries {
+        keys,
+        orphan_values,
+    })
+}
+
+/// Decodes the nk and vk structures found inside free cells, along with their offsets
+fn free_cell_structures(primary_file: &HivePrimaryFile, data: &Bytes) -> Vec<(u32, CellData)> {
+    let mut structures = vec![];
+    for bin in primary_file.hive_bins() {
+        let bin_start = bin.header().offset() as usize;
+        let bin_end = (bin_start + bin.header().size() as usize).min(data.len());
+        let mut position = bin_start + HIVE_HEADER_SIZE;
+        while position + CELL_SIZE_LENGTH <= bin_end {
+            let size = (&data[position..]).get_i32_le();
+            let cell_size = size.unsigned_abs() as usize;
+            if cell_size < CELL_ALIGNMENT || cell_size > bin_end - position {
+                break;
+            }
+            if size > 0 {
+                let cell_end = position + cell_size;
+                let mut inner = position;
+                while inner + CELL_SIZE_LENGTH + CELL_SIG_SIZE <= cell_end {
+                    let sig =
+                        &data[inner + CELL_SIZE_LENGTH..inner + CELL_SIZE_LENGTH + CELL_SIG_SIZE];
+                    let inner_size = (&data[inner..]).get_i32_le().unsigned_abs() as usize;
+                    let cell_data = if (sig == NAMED_KEY_SIG.as_bytes()
+                        || sig == VALUE_KEY_SIG.as_bytes())
+                        && inner_size >= CELL_ALIGNMENT
+                        && inner_size <= cell_end - inner
+                    {
+                        primary_file.decode_cell(inner as u32).ok()
+                    } else {
+                        None
+                    };
+                    // The size field of the first cell covers every cell merged into it, so the
+                    // scan continues after the structure itself rather than after its cell
+                    let structure_size = match &cell_data {
+                        Some(CellData::NamedKey(named_key)) => {
+                            NAMED_KEY_SIZE + named_key.key_name_length() as usize
+                        }
+                        Some(CellData::ValueKey(value_key)) => {
+                            VALUE_KEY_SIZE + value_key.name_length() as usize
+                        }
+                        _ => 0,
+                    };
+                    if let Some(cell_data) = cell_data {
+                        structures.push((inner as u32, cell_data));
+                    }
+                    inner += (CELL_SIZE_LENGTH + CELL_SIG_SIZE + structure_size)
+                        .next_multiple_of(CELL_ALIGNMENT);
+                }
+            }
+            position += cell_size;
+        }
+    }
+    structures
+}
+
+/// Reads the value list of a deleted key, stopping at the end of the list cell
+fn value_list(primary_file: &HivePrimaryFile, named_key: &NamedKey) -> Vec<u32> {
+    if named_key.number_of_key_values() == 0 {
+        return vec![];
+    }
+    let Ok(Some(mut list)) = primary_file.cell_bytes(named_key.key_values_list_offset()) else {
+        return vec![];
+    };
+    let count = (named_key.number_of_key_values() as usize)
+        .min(list.remaining() / OFFSET_LIST_ELEMENT_SIZE);
+    (0..count).map(|_| list.get_u32_le()).collect()
+}
+
+fn recover_value(hive: &Hive, offset: u32, value_key: ValueKey) -> RecoveredValue {
+    let data = if value_key.is_data_resident()
+        || matches!(
+            hive.primary_file().is_allocated(value_key.data_offset()),
+            Ok(false)
+        ) {
+        hive.value_data(&value_key, offset)
+            .ok()
+            .map(|data| RegValue::decode(value_key.data_type(), data))
+    } else {
+        None
+    };
+    RecoveredValue {
+        offset,
+        value_key,
+        data,
+    }
+}
+
+/// Follows the parent offsets of a deleted key through other deleted keys until an allocated key
+/// is reached
+fn key_path(hive: &Hive, named_keys: &HashMap<u32, NamedKey>, offset: u32) -> Option<String> {
+    let mut names = vec![named_keys[&offset].key_name()];
+    let mut parent_offset = named_keys[&offset].parent_key_offset();
+    while names.len() <= MAX_KEY_DEPTH {
+        if let Ok(parent) = hive.key(parent_offset) {
+            let parent_path = parent.path();
+            names.reverse();
+            let path = names.join("\\");
+            return Some(if parent_path.is_empty() {
+                path
+            } else {
+                format!("{}\\{}", parent_path, path)
+            });
+        }
+        let parent = named_keys.get(&parent_offset)?;
+        if parent.is_root() {
+            names.reverse();
+            return Some(names.join("\\"));
+        }
+        names.push(parent.key_name());
+        parent_offset = parent.parent_key_offset();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{parse_registry, REG_DWORD};
+    use crate::test_hive::{named_key, value_key, HiveBuilder, TestKey, CELL_OFFSET_NONE};
+    use rayon::ThreadPoolBuilder;
+
+    const NAMED_KEY_CELL_SIZE: usize = 88;
+    const VALUE_KEY_CELL_SIZE: usize = 32;
+
+    /// Builds a hive holding a deleted key followed by a deleted value, either as two free cells
+    /// or merged into one
+    fn deleted_hive(merged: bool) -> Hive {
+        let mut builder = HiveBuilder::new();
+        let root = builder.key(&TestKey::new("ROOT"), CELL_OFFSET_NONE);
+        let mut deleted_key = named_key("Gone", 0x20, root, (0, CELL_OFFSET_NONE), (0, 0));
+        let deleted_value = value_key("val", REG_DWORD, 0x8000_0004, 42);
+        if merged {
+            deleted_key.resize(NAMED_KEY_CELL_SIZE - CELL_SIZE_LENGTH, 0);
+            deleted_key.extend((VALUE_KEY_CELL_SIZE as i32).to_le_bytes());
+            deleted_key.extend(&deleted_value);
+            builder.free_cell(&deleted_key, NAMED_KEY_CELL_SIZE + VALUE_KEY_CELL_SIZE);
+        } else {
+            builder.free_cell(&deleted_key, NAMED_KEY_CELL_SIZE);
+            builder.free_cell(&deleted_value, VALUE_KEY_CELL_SIZE);
+        }
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        Hive::new(parse_registry(builder.finish(root), &pool).unwrap())
+    }
+
+    #[test]
+    fn recovers_every_structure_of_a_free_cell() {
+        for merged in [false, true] {
+            let deleted = recover_deleted(&deleted_hive(merged)).unwrap();
+            let keys: Vec<_> = deleted.keys().iter().map(|key| key.path()).collect();
+            assert_eq!(keys, [Some("Gone")], "merged: {}", merged);
+            let values: Vec<_> = deleted
+                .orphan_values()
+                .iter()
+                .map(|value| (value.name().into_owned(), value.data().cloned()))
+                .collect();
+            assert_eq!(
+                values,
+                [("val".to_string(), Some(RegValue::Dword(42)))],
+                "merged: {}",
+                merged
+            );
+        }
+    }
+}
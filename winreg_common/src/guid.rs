@@ -0,0 +1,44 @@
+use std::fmt;
+
+pub const GUID_SIZE: usize = 16;
+
+/// A GUID as stored on disk, with the first three fields in little-endian byte order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Guid {
+    bytes: [u8; GUID_SIZE],
+}
+
+impl Guid {
+    pub fn from_bytes(bytes: [u8; GUID_SIZE]) -> Self {
+        Guid { bytes }
+    }
+
+    pub fn bytes(&self) -> &[u8; GUID_SIZE] {
+        &self.bytes
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.bytes == [0; GUID_SIZE]
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.bytes;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8],
+            b[9],
+            b[10],
+            b[11],
+            b[12],
+            b[13],
+            b[14],
+            b[15]
+        )
+    }
+}
@@ -1,25 +1,120 @@
-use bytes::{Buf, Bytes};
-use rayon::iter::IntoParallelRefMutIterator;
+use crate::guid::{Guid, GUID_SIZE};
+use crate::recovery::{self, RecoveryError};
+use crate::security::SecurityDescriptor;
+use crate::stream::{PageCache, ReadSeek, DEFAULT_CACHED_PAGES};
+use crate::timestamp::FileTime;
+use bytes::{Buf, Bytes, BytesMut};
+use memmap2::Mmap;
+use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
 use rayon::ThreadPool;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use utf16string::WStr;
 
-const HIVE_HEADER_SIZE: usize = 32;
+pub(crate) const HIVE_HEADER_SIZE: usize = 32;
+const HIVE_BIN_ALIGNMENT: usize = 4096;
+pub(crate) const CELL_SIZE_LENGTH: usize = 4;
+const CELL_OFFSET_NONE: u32 = 0xFFFF_FFFF;
+const KEY_HIVE_ENTRY: u16 = 0x0004;
+pub(crate) const MAX_KEY_DEPTH: usize = 512;
+const KEY_PATH_SEPARATOR: char = '\\';
+const NAME_HASH_MULTIPLIER: u32 = 37;
+const NAME_HINT_SIZE: usize = 4;
+const DEFAULT_VALUE_NAME: &str = "(default)";
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_RESIDENT_FLAG: u32 = 0x8000_0000;
+const DATA_RESIDENT_MAX_SIZE: usize = 4;
+const BIG_DATA_SEGMENT_SIZE: usize = 16344;
+pub const HIVE_BASE_BLOCK_SIZE: usize = 4096;
+const HIVE_BASE_BLOCK_CHECKSUM_OFFSET: usize = 508;
+const HIVE_FILE_NAME_SIZE: usize = 64;
+const GUID_SIGNATURE_SIZE: usize = 4;
+pub(crate) const NAMED_KEY_SIZE: usize = 74;
+pub(crate) const VALUE_KEY_SIZE: usize = 18;
+const SECURITY_KEY_SIZE: usize = 18;
+const DATA_BLOCK_SIZE: usize = 6;
+const LIST_HEADER_SIZE: usize = 2;
+pub(crate) const CELL_SIG_SIZE: usize = 2;
 const HIVE_BASE_BLOCK_SIG: &str = "regf";
 const HIVE_BIN_HEADER_SIG: &str = "hbin";
 const INDEX_LEAF_SIG: &str = "li";
 const FAST_LEAF_SIG: &str = "lf";
 const HASH_LEAF_SIG: &str = "lh";
 const INDEX_ROOT_SIG: &str = "ri";
-const NAMED_KEY_SIG: &str = "nk";
-const VALUE_KEY_SIG: &str = "vk";
+pub(crate) const NAMED_KEY_SIG: &str = "nk";
+pub(crate) const VALUE_KEY_SIG: &str = "vk";
 const KEY_SECURITY_SIG: &str = "sk";
 const DATA_BLOCK_SIG: &str = "db";
 
+pub const REG_NONE: u32 = 0;
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_DWORD_BIG_ENDIAN: u32 = 5;
+pub const REG_LINK: u32 = 6;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_RESOURCE_LIST: u32 = 8;
+pub const REG_FULL_RESOURCE_DESCRIPTOR: u32 = 9;
+pub const REG_RESOURCE_REQUIREMENTS_LIST: u32 = 10;
+pub const REG_QWORD: u32 = 11;
+
+const DATA_TYPE_NAMES: [(u32, &str); 12] = [
+    (REG_NONE, "REG_NONE"),
+    (REG_SZ, "REG_SZ"),
+    (REG_EXPAND_SZ, "REG_EXPAND_SZ"),
+    (REG_BINARY, "REG_BINARY"),
+    (REG_DWORD, "REG_DWORD"),
+    (REG_DWORD_BIG_ENDIAN, "REG_DWORD_BIG_ENDIAN"),
+    (REG_LINK, "REG_LINK"),
+    (REG_MULTI_SZ, "REG_MULTI_SZ"),
+    (REG_RESOURCE_LIST, "REG_RESOURCE_LIST"),
+    (REG_FULL_RESOURCE_DESCRIPTOR, "REG_FULL_RESOURCE_DESCRIPTOR"),
+    (
+        REG_RESOURCE_REQUIREMENTS_LIST,
+        "REG_RESOURCE_REQUIREMENTS_LIST",
+    ),
+    (REG_QWORD, "REG_QWORD"),
+];
+
 #[derive(Debug)]
 /// Format specification: https://github.com/libyal/libregf/blob/main/documentation/Windows%20NT%20Registry%20File%20(REGF)%20format.asciidoc
 pub struct HivePrimaryFile {
     base_block: HiveBaseBlock,
     hive_bins: Vec<HiveBin>,
+    hive_bins_data: HiveBinsData,
+    cell_index: HashMap<u32, (usize, usize)>,
+    parse_mode: ParseMode,
+}
+
+/// The hive bins data, either held in memory or read on demand from a stream
+#[derive(Debug)]
+enum HiveBinsData {
+    Memory {
+        data: Bytes,
+        // End of each bin, in order, as the bins follow each other from the start of the data
+        bin_ends: Vec<usize>,
+    },
+    Stream {
+        cache: Mutex<PageCache>,
+        len: usize,
+        // Ranges looked up so far, either inside a bin or outside of any bin, keyed by their start
+        ranges: Mutex<BTreeMap<usize, BinRange>>,
+    },
+}
+
+/// A range of the hive bins data of a stream, as found while looking up bins
+#[derive(Debug, Clone, Copy)]
+struct BinRange {
+    end: usize,
+    is_bin: bool,
 }
 
 #[derive(Debug)]
@@ -34,10 +129,18 @@ pub struct HiveBaseBlock {
     root_key_offset: u32,
     hive_bins_data_size: u32,
     clustering_factor: u32,
-    file_name: Vec<u8>,
-    // reserved 396 bytes
+    file_name: Bytes,
+    // Fields used since Windows 10, zero in older hives
+    rm_id: Guid,
+    log_id: Guid,
+    flags: u32,
+    tm_id: Guid,
+    guid_signature: [u8; GUID_SIGNATURE_SIZE],
+    last_reorganized_timestamp: u64,
+    // reserved 332 bytes
     // reserved_0: [u8],
     checksum: u32,
+    calculated_checksum: u32,
     // reserved 3576 bytes (Populated on Windows 10
     // reserved_1: [u8],
     boot_type: u32,
@@ -63,10 +166,11 @@ pub struct HiveBinHeader {
 #[derive(Debug)]
 pub struct HiveBinCell {
     size: i32,
+    offset: u32,
     cell_data: CellData,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CellData {
     IndexLeaf(IndexLeaf),
     FastLeaf(FastLeaf),
@@ -78,53 +182,53 @@ pub enum CellData {
     DataBlock(DataBlock),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IndexLeaf {
     number_of_elements: u16,
     elements: Vec<IndexLeafElement>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IndexLeafElement {
     key_node_offset: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FastLeaf {
     number_of_elements: u16,
     elements: Vec<FastLeafElement>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FastLeafElement {
     key_node_offset: u32,
-    name_hint: Vec<u8>,
+    name_hint: [u8; NAME_HINT_SIZE],
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HashLeaf {
     number_of_elements: u16,
     elements: Vec<HashLeafElement>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HashLeafElement {
     key_node_offset: u32,
     name_hash: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IndexRoot {
     number_of_elements: u16,
     elements: Vec<IndexRootElement>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IndexRootElement {
     subkeys_list_offset: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NamedKey {
     flags: u16,
     last_written_timestamp: u64,
@@ -145,10 +249,10 @@ pub struct NamedKey {
     work_var: u32,
     key_name_length: u16,
     class_name_length: u16,
-    key_name: Vec<u8>, // Or ASCII extended(?)
+    key_name: Bytes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ValueKey {
     name_length: u16,
     data_size: u32,
@@ -156,10 +260,10 @@ pub struct ValueKey {
     data_type: u32,
     flags: u16,
     spare: u16,
-    value_name: Vec<u8>,
+    value_name: Bytes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SecurityKey {
     // reserved 2 bytes
     // reserved: [u8]
@@ -167,66 +271,582 @@ pub struct SecurityKey {
     next_security_key_offset: u32,
     reference_count: u32,
     nt_security_descriptor_size: u32,
-    nt_security_descriptor: Vec<u8>,
+    nt_security_descriptor: Bytes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DataBlock {
     number_of_segments: u16,
     data_block_list_offset: u32,
 }
 
+/// How a base block checksum mismatch is handled while parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumMode {
+    /// Reject hives with an invalid checksum
+    Strict,
+    /// Continue parsing hives with an invalid checksum, the mismatch is available from
+    /// [`HiveBaseBlock::checksum_error`]
+    #[default]
+    Lenient,
+}
+
+/// When the cells of a hive are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Decode every cell in every bin while parsing
+    #[default]
+    Eager,
+    /// Only read the bin headers while parsing and decode cells when navigation reaches them
+    Lazy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    checksum_mode: ChecksumMode,
+    parse_mode: ParseMode,
+    replay_logs: bool,
+}
+
+/// The on-disk structure being parsed when a [`HiveParseError`] occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HiveStructure {
+    BaseBlock,
+    HiveBinHeader,
+    Cell,
+    IndexLeaf,
+    FastLeaf,
+    HashLeaf,
+    IndexRoot,
+    SubkeyList,
+    NamedKey,
+    ValueKey,
+    SecurityKey,
+    SecurityDescriptor,
+    DataBlock,
+    ValueList,
+    ValueData,
+    ClassName,
+}
+
+/// Error raised while parsing a hive. Offsets are absolute file offsets of the structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HiveParseError {
+    /// The structure extends past the end of the available data
+    Truncated {
+        structure: HiveStructure,
+        offset: u64,
+        needed: usize,
+        available: usize,
+    },
+    /// The structure does not start with its expected signature
+    InvalidSignature {
+        structure: HiveStructure,
+        offset: u64,
+        expected: &'static str,
+        found: Vec<u8>,
+    },
+    /// A cell offset does not point to an allocated cell of the expected structure
+    InvalidCell {
+        structure: HiveStructure,
+        offset: u64,
+    },
+    /// The stored base block checksum does not match the calculated checksum
+    InvalidChecksum {
+        offset: u64,
+        stored: u32,
+        calculated: u32,
+    },
+    /// The structure is present but its contents are inconsistent
+    Malformed {
+        structure: HiveStructure,
+        offset: u64,
+        msg: &'static str,
+    },
+    /// The structure could not be read from the stream the hive is parsed from
+    Io {
+        structure: HiveStructure,
+        offset: u64,
+        msg: String,
+    },
+}
+
+impl HiveParseError {
+    pub fn structure(&self) -> HiveStructure {
+        match self {
+            HiveParseError::Truncated { structure, .. }
+            | HiveParseError::InvalidSignature { structure, .. }
+            | HiveParseError::InvalidCell { structure, .. }
+            | HiveParseError::Malformed { structure, .. }
+            | HiveParseError::Io { structure, .. } => *structure,
+            HiveParseError::InvalidChecksum { .. } => HiveStructure::BaseBlock,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        match self {
+            HiveParseError::Truncated { offset, .. }
+            | HiveParseError::InvalidSignature { offset, .. }
+            | HiveParseError::InvalidCell { offset, .. }
+            | HiveParseError::InvalidChecksum { offset, .. }
+            | HiveParseError::Malformed { offset, .. }
+            | HiveParseError::Io { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for HiveStructure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HiveStructure::BaseBlock => "base block",
+            HiveStructure::HiveBinHeader => "hbin header",
+            HiveStructure::Cell => "cell",
+            HiveStructure::IndexLeaf => "li list",
+            HiveStructure::FastLeaf => "lf list",
+            HiveStructure::HashLeaf => "lh list",
+            HiveStructure::IndexRoot => "ri list",
+            HiveStructure::SubkeyList => "subkey list",
+            HiveStructure::NamedKey => "nk",
+            HiveStructure::ValueKey => "vk",
+            HiveStructure::SecurityKey => "sk",
+            HiveStructure::SecurityDescriptor => "security descriptor",
+            HiveStructure::DataBlock => "db",
+            HiveStructure::ValueList => "value list",
+            HiveStructure::ValueData => "value data",
+            HiveStructure::ClassName => "class name",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for HiveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveParseError::Truncated {
+                structure,
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "Truncated {} at offset {:#x}: needed {} bytes, {} available",
+                structure, offset, needed, available
+            ),
+            HiveParseError::InvalidSignature {
+                structure,
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Invalid {} signature at offset {:#x}: expected '{}', found '{}'",
+                structure,
+                offset,
+                expected,
+                found.escape_ascii()
+            ),
+            HiveParseError::InvalidCell { structure, offset } => {
+                write!(f, "No {} cell at offset {:#x}", structure, offset)
+            }
+            HiveParseError::InvalidChecksum {
+                offset,
+                stored,
+                calculated,
+            } => write!(
+                f,
+                "Invalid base block checksum at offset {:#x}: stored {:#010x}, calculated {:#010x}",
+                offset, stored, calculated
+            ),
+            HiveParseError::Malformed {
+                structure,
+                offset,
+                msg,
+            } => write!(
+                f,
+                "Malformed {} at offset {:#x}: {}",
+                structure, offset, msg
+            ),
+            HiveParseError::Io {
+                structure,
+                offset,
+                msg,
+            } => write!(
+                f,
+                "Failed reading {} at offset {:#x}: {}",
+                structure, offset, msg
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HiveParseError {}
+
+/// Error raised while opening a hive file from disk
 #[derive(Debug)]
-pub struct HiveParseError;
+pub enum HiveOpenError {
+    Io(io::Error),
+    Parse(HiveParseError),
+    /// The hive is dirty and its transaction logs could not be applied
+    Recovery(RecoveryError),
+}
 
-impl HivePrimaryFile {
-    fn build(mut buf: Bytes) -> Result<Self, HiveParseError> {
-        let start = buf.remaining();
+impl fmt::Display for HiveOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveOpenError::Io(error) => write!(f, "failed reading hive file: {}", error),
+            HiveOpenError::Parse(error) => write!(f, "failed parsing hive file: {}", error),
+            HiveOpenError::Recovery(error) => {
+                write!(f, "failed replaying transaction logs: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HiveOpenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HiveOpenError::Io(error) => Some(error),
+            HiveOpenError::Parse(error) => Some(error),
+            HiveOpenError::Recovery(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for HiveOpenError {
+    fn from(error: io::Error) -> Self {
+        HiveOpenError::Io(error)
+    }
+}
+
+impl From<HiveParseError> for HiveOpenError {
+    fn from(error: HiveParseError) -> Self {
+        HiveOpenError::Parse(error)
+    }
+}
+
+impl From<RecoveryError> for HiveOpenError {
+    fn from(error: RecoveryError) -> Self {
+        HiveOpenError::Recovery(error)
+    }
+}
 
+impl HivePrimaryFile {
+    fn build(mut buf: Bytes, options: &ParseOptions) -> Result<Self, HiveParseError> {
         let base_block = HiveBaseBlock::build(&mut buf)?;
+        base_block.check(options)?;
+
+        // Cell offsets are relative to the start of the hive bins data, right after the base block
+        let hive_bins_data = buf;
+        let end = hive_bins_data
+            .len()
+            .min(base_block.hive_bins_data_size() as usize);
 
         let mut bin_blocks = vec![];
         let mut offset = 0;
-        while start > offset {
-            let hive_bin_header = HiveBinHeader::build(&mut &buf[offset..offset + 32]).unwrap();
-            if offset + hive_bin_header.size() as usize >= buf.len() {
+        while offset + HIVE_HEADER_SIZE <= end {
+            let hive_bin_header = HiveBinHeader::build(
+                &mut &hive_bins_data[offset..offset + HIVE_HEADER_SIZE],
+                file_offset(offset as u32),
+            )?;
+            let size = hive_bin_header.size() as usize;
+            if size < HIVE_HEADER_SIZE || offset + size > end {
                 break;
             }
-            bin_blocks.push(&buf[offset..offset + 32 + hive_bin_header.size() as usize]);
-            offset += hive_bin_header.size() as usize;
+            bin_blocks.push((offset as u32, hive_bins_data.slice(offset..offset + size)));
+            offset += size;
         }
 
-        let hive_bins = bin_blocks
-            .par_iter_mut()
-            .map(|bin| {
-                let mut cells: Vec<HiveBinCell> = vec![];
-                let header = HiveBinHeader::build(bin).unwrap();
-                while let Some(cell) = HiveBinCell::build(bin) {
-                    cells.push(cell);
-                }
-                HiveBin::new(header, cells)
-            })
+        let bin_ends = bin_blocks
+            .iter()
+            .map(|(offset, bin)| *offset as usize + bin.len())
             .collect();
+        let parse_mode = options.parse_mode();
+        let hive_bins: Vec<HiveBin> = bin_blocks
+            .into_par_iter()
+            .map(|(offset, bin)| HiveBin::build(offset, bin, parse_mode))
+            .collect::<Result<_, _>>()?;
+
+        let mut cell_index = HashMap::new();
+        for (bin_index, bin) in hive_bins.iter().enumerate() {
+            for (cell_index_in_bin, cell) in bin.cells.iter().enumerate() {
+                // Free cells are never referenced by the live tree, see the deleted module
+                if cell.is_allocated() {
+                    cell_index.insert(cell.offset, (bin_index, cell_index_in_bin));
+                }
+            }
+        }
 
         Ok(HivePrimaryFile {
             base_block,
             hive_bins,
+            hive_bins_data: HiveBinsData::Memory {
+                data: hive_bins_data,
+                bin_ends,
+            },
+            cell_index,
+            parse_mode,
+        })
+    }
+
+    /// Reads the base block from the stream and keeps the stream to read cells from on demand.
+    /// Cells are always decoded lazily and bins are not read up front.
+    fn build_from_reader(
+        mut reader: Box<dyn ReadSeek>,
+        options: &ParseOptions,
+    ) -> Result<Self, HiveOpenError> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut block = Vec::with_capacity(HIVE_BASE_BLOCK_SIZE);
+        (&mut reader)
+            .take(HIVE_BASE_BLOCK_SIZE as u64)
+            .read_to_end(&mut block)?;
+        let base_block = HiveBaseBlock::build(&mut Bytes::from(block))?;
+        base_block.check(options)?;
+
+        let cache = PageCache::new(reader, HIVE_BASE_BLOCK_SIZE as u64, DEFAULT_CACHED_PAGES)?;
+        let len = cache.len().min(base_block.hive_bins_data_size() as usize);
+        Ok(HivePrimaryFile {
+            base_block,
+            hive_bins: vec![],
+            hive_bins_data: HiveBinsData::Stream {
+                cache: Mutex::new(cache),
+                len,
+                ranges: Mutex::new(BTreeMap::new()),
+            },
+            cell_index: HashMap::new(),
+            parse_mode: ParseMode::Lazy,
         })
     }
+
+    pub fn base_block(&self) -> &HiveBaseBlock {
+        &self.base_block
+    }
+
+    /// Returns the bins of the hive, which is empty for hives read from a stream
+    pub fn hive_bins(&self) -> &[HiveBin] {
+        &self.hive_bins
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Returns the allocated cell starting at the given offset, relative to the hive bins data.
+    /// Always returns `None` for hives parsed in [`ParseMode::Lazy`], use
+    /// [`HivePrimaryFile::cell_data`] to support both modes.
+    pub fn cell(&self, offset: u32) -> Option<&HiveBinCell> {
+        let (bin_index, cell_index) = *self.cell_index.get(&offset)?;
+        Some(&self.hive_bins[bin_index].cells[cell_index])
+    }
+
+    /// Returns the data of the allocated cell starting at the given offset. Hives parsed in
+    /// [`ParseMode::Lazy`] decode the cell on every call, failing only when it can not be read
+    /// from the stream.
+    pub fn cell_data(&self, offset: u32) -> Result<Option<Cow<'_, CellData>>, HiveParseError> {
+        match self.parse_mode {
+            ParseMode::Eager => Ok(self
+                .cell(offset)
+                .map(|cell| Cow::Borrowed(cell.cell_data()))),
+            ParseMode::Lazy => {
+                if !self.is_allocated(offset)? {
+                    return Ok(None);
+                }
+                match self.decode_cell(offset) {
+                    Ok(cell_data) => Ok(Some(Cow::Owned(cell_data))),
+                    Err(error @ HiveParseError::Io { .. }) => Err(error),
+                    Err(_) => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Returns true when the cell starting at the given offset is allocated
+    pub fn is_allocated(&self, offset: u32) -> Result<bool, HiveParseError> {
+        Ok(self
+            .hive_bins_data
+            .read(offset as usize, CELL_SIZE_LENGTH)?
+            .is_some_and(|mut size| size.get_i32_le() < 0))
+    }
+
+    /// Returns the hive bins data, or `None` for hives read from a stream
+    pub fn hive_bins_data(&self) -> Option<&Bytes> {
+        match &self.hive_bins_data {
+            HiveBinsData::Memory { data, .. } => Some(data),
+            HiveBinsData::Stream { .. } => None,
+        }
+    }
+
+    /// Decodes the cell starting at the given offset from the raw hive bins data, whether it is
+    /// allocated or not
+    pub fn decode_cell(&self, offset: u32) -> Result<CellData, HiveParseError> {
+        let mut data = self
+            .cell_bytes(offset)?
+            .ok_or(HiveParseError::InvalidCell {
+                structure: HiveStructure::Cell,
+                offset: file_offset(offset),
+            })?;
+        CellData::build(&mut data, file_offset(offset) + CELL_SIZE_LENGTH as u64)
+    }
+
+    /// Returns the raw data of the cell starting at the given offset, excluding its size field.
+    /// Used for cells without a signature, such as value lists and value data. Returns `None`
+    /// when the cell extends past the end of its bin.
+    pub fn cell_bytes(&self, offset: u32) -> Result<Option<Bytes>, HiveParseError> {
+        let start = offset as usize;
+        let Some(mut size) = self.hive_bins_data.read(start, CELL_SIZE_LENGTH)? else {
+            return Ok(None);
+        };
+        let size = size.get_i32_le().unsigned_abs() as usize;
+        if size < CELL_SIZE_LENGTH {
+            return Ok(None);
+        }
+        // Checked before reading, as a hostile size would otherwise make a stream read
+        // allocate up to the size of the whole stream
+        match self.hive_bins_data.bin_end(start)? {
+            Some(bin_end) if start.saturating_add(size) <= bin_end => {}
+            _ => return Ok(None),
+        }
+        self.hive_bins_data
+            .read(start + CELL_SIZE_LENGTH, size - CELL_SIZE_LENGTH)
+    }
+}
+
+impl HiveBinsData {
+    /// Reads `len` bytes starting at the given offset, relative to the hive bins data. Returns
+    /// `None` when the range is out of bounds.
+    fn read(&self, start: usize, len: usize) -> Result<Option<Bytes>, HiveParseError> {
+        let end = start.checked_add(len);
+        match self {
+            HiveBinsData::Memory { data, .. } => Ok(end
+                .filter(|end| *end <= data.len())
+                .map(|end| data.slice(start..end))),
+            HiveBinsData::Stream {
+                cache,
+                len: data_len,
+                ..
+            } => {
+                if end.is_none_or(|end| end > *data_len) {
+                    return Ok(None);
+                }
+                let mut cache = cache
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                cache.read(start, len).map_err(|error| HiveParseError::Io {
+                    structure: HiveStructure::Cell,
+                    offset: file_offset(start as u32),
+                    msg: error.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Returns the end of the bin containing the given offset, or `None` when the offset is not
+    /// inside a bin
+    fn bin_end(&self, offset: usize) -> Result<Option<usize>, HiveParseError> {
+        let (len, ranges) = match self {
+            HiveBinsData::Memory { bin_ends, .. } => {
+                let index = bin_ends.partition_point(|end| *end <= offset);
+                return Ok(bin_ends.get(index).copied());
+            }
+            HiveBinsData::Stream { len, ranges, .. } => (*len, ranges),
+        };
+        if offset >= len {
+            return Ok(None);
+        }
+        let lock_ranges = || {
+            ranges
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        };
+        let known_range = |offset: usize| {
+            lock_ranges()
+                .range(..=offset)
+                .next_back()
+                .map(|(_, range)| *range)
+                .filter(|range| offset < range.end)
+        };
+        if let Some(range) = known_range(offset) {
+            return Ok(range.is_bin.then_some(range.end));
+        }
+
+        // Bins start at page boundaries, so the nearest bin header before the offset is the
+        // one of the bin containing it, if any. The scan stops at the ranges found by earlier
+        // lookups, and a miss is remembered as well, so no page is scanned twice.
+        let page_end = (offset - offset % HIVE_BIN_ALIGNMENT + HIVE_BIN_ALIGNMENT).min(len);
+        let mut start = offset - offset % HIVE_BIN_ALIGNMENT;
+        let gap_start = loop {
+            if let Some(range) = known_range(start) {
+                break range.end;
+            }
+            if let Some(mut header) = self.read(start, HIVE_HEADER_SIZE)? {
+                if header.starts_with(HIVE_BIN_HEADER_SIG.as_bytes()) {
+                    let size = HiveBinHeader::build(&mut header, file_offset(start as u32))
+                        .ok()
+                        .filter(|header| {
+                            header.offset() as usize == start
+                                && header.size() as usize >= HIVE_HEADER_SIZE
+                                && start + header.size() as usize <= len
+                        })
+                        .map(|header| header.size() as usize);
+                    let Some(size) = size else {
+                        break start;
+                    };
+                    let end = start + size;
+                    lock_ranges().insert(start, BinRange { end, is_bin: true });
+                    if offset < end {
+                        return Ok(Some(end));
+                    }
+                    break end;
+                }
+            }
+            if start == 0 {
+                break 0;
+            }
+            start -= HIVE_BIN_ALIGNMENT;
+        };
+        lock_ranges().insert(
+            gap_start,
+            BinRange {
+                end: page_end,
+                is_bin: false,
+            },
+        );
+        Ok(None)
+    }
 }
 
 impl HiveBin {
-    fn new(header: HiveBinHeader, cells: Vec<HiveBinCell>) -> Self {
-        HiveBin { header, cells }
+    fn build(offset: u32, mut buf: Bytes, parse_mode: ParseMode) -> Result<Self, HiveParseError> {
+        let header = HiveBinHeader::build(&mut buf, file_offset(offset))?;
+        let mut cell_offset = offset + HIVE_HEADER_SIZE as u32;
+        let mut cells: Vec<HiveBinCell> = vec![];
+        if parse_mode == ParseMode::Eager {
+            while let Some(cell) = HiveBinCell::build(&mut buf, &mut cell_offset) {
+                cells.push(cell);
+            }
+        }
+        Ok(HiveBin { header, cells })
+    }
+
+    pub fn header(&self) -> &HiveBinHeader {
+        &self.header
+    }
+
+    /// Returns the decoded cells of the bin, which is empty for hives parsed in
+    /// [`ParseMode::Lazy`]
+    pub fn cells(&self) -> &[HiveBinCell] {
+        &self.cells
     }
 }
 
 impl HiveBaseBlock {
     pub fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
-        let sig = &*read_arr(buf, 4);
-        if sig != HIVE_BASE_BLOCK_SIG.as_bytes() {
-            return Err(HiveParseError);
-        }
+        let structure = HiveStructure::BaseBlock;
+        ensure_remaining(buf, HIVE_BASE_BLOCK_SIZE, structure, 0)?;
+        let mut block = buf.copy_to_bytes(HIVE_BASE_BLOCK_SIZE);
+        let calculated_checksum = HiveBaseBlock::calculate_checksum(&block);
+
+        let buf = &mut block;
+        check_signature(buf, HIVE_BASE_BLOCK_SIG, structure, 0)?;
         let primary_sequence_number = buf.get_u32_le();
         let secondary_sequence_number = buf.get_u32_le();
         let last_written_timestamp = buf.get_u64_le();
@@ -237,10 +857,17 @@ impl HiveBaseBlock {
         let root_key_offset = buf.get_u32_le();
         let hive_bins_data_size = buf.get_u32_le();
         let clustering_factor = buf.get_u32_le();
-        let file_name = read_arr(buf, 64);
+        let file_name = buf.copy_to_bytes(HIVE_FILE_NAME_SIZE);
+        let rm_id = read_guid(buf);
+        let log_id = read_guid(buf);
+        let flags = buf.get_u32_le();
+        let tm_id = read_guid(buf);
+        let mut guid_signature = [0; GUID_SIGNATURE_SIZE];
+        buf.copy_to_slice(&mut guid_signature);
+        let last_reorganized_timestamp = buf.get_u64_le();
 
-        // Skip reserved sector (Has data on Windows 10). See specification
-        buf.advance(396);
+        // Skip reserved sector. See specification
+        buf.advance(332);
 
         let checksum = buf.get_u32_le();
 
@@ -262,19 +889,160 @@ impl HiveBaseBlock {
             hive_bins_data_size,
             clustering_factor,
             file_name,
+            rm_id,
+            log_id,
+            flags,
+            tm_id,
+            guid_signature,
+            last_reorganized_timestamp,
             checksum,
+            calculated_checksum,
             boot_type,
             boot_recover,
         })
     }
+
+    pub fn primary_sequence_number(&self) -> u32 {
+        self.primary_sequence_number
+    }
+
+    pub fn secondary_sequence_number(&self) -> u32 {
+        self.secondary_sequence_number
+    }
+
+    /// A hive is dirty when a write was interrupted, leaving changes only in its transaction logs
+    pub fn is_dirty(&self) -> bool {
+        self.primary_sequence_number != self.secondary_sequence_number
+    }
+
+    pub fn last_written_timestamp(&self) -> FileTime {
+        FileTime::new(self.last_written_timestamp)
+    }
+
+    pub fn major_version(&self) -> u32 {
+        self.major_version
+    }
+
+    pub fn minor_version(&self) -> u32 {
+        self.minor_version
+    }
+
+    pub fn file_type(&self) -> u32 {
+        self.file_type
+    }
+
+    pub fn file_format(&self) -> u32 {
+        self.file_format
+    }
+
+    pub fn root_key_offset(&self) -> u32 {
+        self.root_key_offset
+    }
+
+    pub fn hive_bins_data_size(&self) -> u32 {
+        self.hive_bins_data_size
+    }
+
+    pub fn clustering_factor(&self) -> u32 {
+        self.clustering_factor
+    }
+
+    /// Returns the raw UTF-16LE file name, the last 31 characters of the hive's path
+    pub fn file_name(&self) -> &Bytes {
+        &self.file_name
+    }
+
+    /// Returns the file name decoded up to its terminating null character
+    pub fn decoded_file_name(&self) -> String {
+        decode_utf16le_string(&self.file_name)
+    }
+
+    /// Returns the resource manager GUID of the Kernel Transaction Manager
+    pub fn rm_id(&self) -> Guid {
+        self.rm_id
+    }
+
+    pub fn log_id(&self) -> Guid {
+        self.log_id
+    }
+
+    /// Returns the hive flags: 0x1 when the Kernel Transaction Manager locked the hive and
+    /// 0x2 for a defragmented hive or, since Windows 10, a hive supporting layered keys
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the transaction manager GUID of the Kernel Transaction Manager
+    pub fn tm_id(&self) -> Guid {
+        self.tm_id
+    }
+
+    /// Returns the GUID signature, which is `rmtm` when the GUID fields are set
+    pub fn guid_signature(&self) -> &[u8] {
+        &self.guid_signature
+    }
+
+    pub fn last_reorganized_timestamp(&self) -> FileTime {
+        FileTime::new(self.last_reorganized_timestamp)
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.checksum
+    }
+
+    pub fn calculated_checksum(&self) -> u32 {
+        self.calculated_checksum
+    }
+
+    pub fn is_checksum_valid(&self) -> bool {
+        self.checksum == self.calculated_checksum
+    }
+
+    /// Calculates the XOR-32 checksum of the first 508 bytes of a base block
+    pub fn calculate_checksum(block: &[u8]) -> u32 {
+        let checksum = block[..HIVE_BASE_BLOCK_CHECKSUM_OFFSET.min(block.len())]
+            .chunks_exact(4)
+            .fold(0, |checksum, dword| {
+                checksum ^ u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]])
+            });
+        match checksum {
+            0xFFFF_FFFF => 0xFFFF_FFFE,
+            0 => 1,
+            checksum => checksum,
+        }
+    }
+
+    /// Returns the checksum mismatch as an error, or `None` when the checksum is valid
+    pub fn checksum_error(&self) -> Option<HiveParseError> {
+        (!self.is_checksum_valid()).then(|| HiveParseError::InvalidChecksum {
+            offset: HIVE_BASE_BLOCK_CHECKSUM_OFFSET as u64,
+            stored: self.checksum(),
+            calculated: self.calculated_checksum(),
+        })
+    }
+
+    /// Verifies the checksum, rejecting a mismatch in [`ChecksumMode::Strict`]
+    fn check(&self, options: &ParseOptions) -> Result<(), HiveParseError> {
+        match self.checksum_error() {
+            Some(error) if options.checksum_mode() == ChecksumMode::Strict => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn boot_type(&self) -> u32 {
+        self.boot_type
+    }
+
+    pub fn boot_recover(&self) -> u32 {
+        self.boot_recover
+    }
 }
 
 impl HiveBinHeader {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
-        let sig = &*read_arr(buf, 4);
-        if sig != HIVE_BIN_HEADER_SIG.as_bytes() {
-            return Err(HiveParseError);
-        }
+    fn build(buf: &mut impl Buf, file_offset: u64) -> Result<Self, HiveParseError> {
+        let structure = HiveStructure::HiveBinHeader;
+        ensure_remaining(buf, HIVE_HEADER_SIZE, structure, file_offset)?;
+        check_signature(buf, HIVE_BIN_HEADER_SIG, structure, file_offset)?;
         let offset = buf.get_u32_le();
         let size = buf.get_u32_le();
 
@@ -298,68 +1066,98 @@ impl HiveBinHeader {
     pub fn size(&self) -> u32 {
         self.size
     }
+
+    pub fn timestamp(&self) -> FileTime {
+        FileTime::new(self.timestamp)
+    }
+
+    pub fn spare(&self) -> u32 {
+        self.spare
+    }
 }
 
 impl HiveBinCell {
-    fn build(buf: &mut impl Buf) -> Option<Self> {
+    fn build(buf: &mut Bytes, offset: &mut u32) -> Option<Self> {
         loop {
-            let data_start_pos = buf.remaining();
-            if data_start_pos - HIVE_HEADER_SIZE == 0 {
+            if buf.remaining() < CELL_SIZE_LENGTH {
                 return None;
             }
 
+            let cell_offset = *offset;
             let size = buf.get_i32_le();
-            let cell_data = CellData::build(buf);
-
-            match cell_data {
-                Ok(data) => {
-                    let end_pos = buf.remaining();
-                    let walked = data_start_pos - end_pos;
-                    let advance = size.unsigned_abs() as usize - walked;
-                    buf.advance(advance);
+            let cell_size = size.unsigned_abs() as usize;
+            if cell_size < CELL_SIZE_LENGTH * 2 || cell_size - CELL_SIZE_LENGTH > buf.remaining() {
+                return None;
+            }
+            let mut cell = buf.split_to(cell_size - CELL_SIZE_LENGTH);
+            *offset += cell_size as u32;
+
+            match CellData::build(
+                &mut cell,
+                file_offset(cell_offset) + CELL_SIZE_LENGTH as u64,
+            ) {
+                Ok(cell_data) => {
                     return Some(HiveBinCell {
                         size,
-                        cell_data: data,
+                        offset: cell_offset,
+                        cell_data,
                     });
                 }
-                Err(_) => {
-                    // Skip faulty cell(?) -- TODO: Is it really a bad cell though?
-                    let end_pos = buf.remaining();
-                    let walked = data_start_pos - end_pos;
-                    let advance = size.unsigned_abs() as usize - walked;
-                    buf.advance(advance);
-                    continue;
-                }
+                // Cells without a signature (value lists, value data, class names) are read raw
+                // through HivePrimaryFile::cell_bytes when they are referenced
+                Err(_) => continue,
             }
         }
     }
 
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
     pub fn cell_data(&self) -> &CellData {
         &self.cell_data
     }
+
+    /// Allocated cells have a negative size, free cells a positive one
+    pub fn is_allocated(&self) -> bool {
+        self.size < 0
+    }
 }
 
 impl CellData {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
-        let sig_bytes = read_arr(buf, 2);
-        let sig = std::str::from_utf8(&sig_bytes).map_err(|_e| HiveParseError)?;
-        match sig {
-            INDEX_LEAF_SIG => Ok(CellData::IndexLeaf(IndexLeaf::build(buf).unwrap())),
-            FAST_LEAF_SIG => Ok(CellData::FastLeaf(FastLeaf::build(buf).unwrap())),
-            HASH_LEAF_SIG => Ok(CellData::HashLeaf(HashLeaf::build(buf).unwrap())),
-            INDEX_ROOT_SIG => Ok(CellData::IndexRoot(IndexRoot::build(buf).unwrap())),
-            NAMED_KEY_SIG => Ok(CellData::NamedKey(NamedKey::build(buf).unwrap())),
-            VALUE_KEY_SIG => Ok(CellData::ValueKey(ValueKey::build(buf).unwrap())),
-            KEY_SECURITY_SIG => Ok(CellData::SecurityKey(SecurityKey::build(buf).unwrap())),
-            DATA_BLOCK_SIG => Ok(CellData::DataBlock(DataBlock::build(buf).unwrap())),
-            _invalid => Err(HiveParseError),
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        ensure_remaining(buf, CELL_SIG_SIZE, HiveStructure::Cell, offset)?;
+        let mut sig_bytes = [0; CELL_SIG_SIZE];
+        buf.copy_to_slice(&mut sig_bytes);
+        match std::str::from_utf8(&sig_bytes).unwrap_or_default() {
+            INDEX_LEAF_SIG => Ok(CellData::IndexLeaf(IndexLeaf::build(buf, offset)?)),
+            FAST_LEAF_SIG => Ok(CellData::FastLeaf(FastLeaf::build(buf, offset)?)),
+            HASH_LEAF_SIG => Ok(CellData::HashLeaf(HashLeaf::build(buf, offset)?)),
+            INDEX_ROOT_SIG => Ok(CellData::IndexRoot(IndexRoot::build(buf, offset)?)),
+            NAMED_KEY_SIG => Ok(CellData::NamedKey(NamedKey::build(buf, offset)?)),
+            VALUE_KEY_SIG => Ok(CellData::ValueKey(ValueKey::build(buf, offset)?)),
+            KEY_SECURITY_SIG => Ok(CellData::SecurityKey(SecurityKey::build(buf, offset)?)),
+            DATA_BLOCK_SIG => Ok(CellData::DataBlock(DataBlock::build(buf, offset)?)),
+            _invalid => Err(HiveParseError::InvalidSignature {
+                structure: HiveStructure::Cell,
+                offset,
+                expected: "li, lf, lh, ri, nk, vk, sk or db",
+                found: sig_bytes.to_vec(),
+            }),
         }
     }
 }
 
 impl IndexLeaf {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        let structure = HiveStructure::IndexLeaf;
+        ensure_remaining(buf, LIST_HEADER_SIZE, structure, offset)?;
         let number_of_elements = buf.get_u16_le();
+        ensure_remaining(buf, number_of_elements as usize * 4, structure, offset)?;
         let mut elements = vec![];
         for _ in 0..number_of_elements {
             elements.push(IndexLeafElement {
@@ -371,16 +1169,30 @@ impl IndexLeaf {
             elements,
         })
     }
+
+    pub fn number_of_elements(&self) -> u16 {
+        self.number_of_elements
+    }
+
+    pub fn elements(&self) -> &[IndexLeafElement] {
+        &self.elements
+    }
 }
 
 impl FastLeaf {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        let structure = HiveStructure::FastLeaf;
+        ensure_remaining(buf, LIST_HEADER_SIZE, structure, offset)?;
         let number_of_elements = buf.get_u16_le();
+        ensure_remaining(buf, number_of_elements as usize * 8, structure, offset)?;
         let mut elements = vec![];
         for _ in 0..number_of_elements {
+            let key_node_offset = buf.get_u32_le();
+            let mut name_hint = [0; NAME_HINT_SIZE];
+            buf.copy_to_slice(&mut name_hint);
             elements.push(FastLeafElement {
-                key_node_offset: buf.get_u32_le(),
-                name_hint: read_arr(buf, 4),
+                key_node_offset,
+                name_hint,
             });
         }
         Ok(FastLeaf {
@@ -388,11 +1200,22 @@ impl FastLeaf {
             elements,
         })
     }
+
+    pub fn number_of_elements(&self) -> u16 {
+        self.number_of_elements
+    }
+
+    pub fn elements(&self) -> &[FastLeafElement] {
+        &self.elements
+    }
 }
 
 impl HashLeaf {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        let structure = HiveStructure::HashLeaf;
+        ensure_remaining(buf, LIST_HEADER_SIZE, structure, offset)?;
         let number_of_elements = buf.get_u16_le();
+        ensure_remaining(buf, number_of_elements as usize * 8, structure, offset)?;
         let mut elements = vec![];
         for _ in 0..number_of_elements {
             elements.push(HashLeafElement {
@@ -405,11 +1228,22 @@ impl HashLeaf {
             elements,
         })
     }
+
+    pub fn number_of_elements(&self) -> u16 {
+        self.number_of_elements
+    }
+
+    pub fn elements(&self) -> &[HashLeafElement] {
+        &self.elements
+    }
 }
 
 impl IndexRoot {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        let structure = HiveStructure::IndexRoot;
+        ensure_remaining(buf, LIST_HEADER_SIZE, structure, offset)?;
         let number_of_elements = buf.get_u16_le();
+        ensure_remaining(buf, number_of_elements as usize * 4, structure, offset)?;
         let mut elements = vec![];
         for _ in 0..number_of_elements {
             elements.push(IndexRootElement {
@@ -421,10 +1255,51 @@ impl IndexRoot {
             elements,
         })
     }
+
+    pub fn number_of_elements(&self) -> u16 {
+        self.number_of_elements
+    }
+
+    pub fn elements(&self) -> &[IndexRootElement] {
+        &self.elements
+    }
+}
+
+impl IndexLeafElement {
+    pub fn key_node_offset(&self) -> u32 {
+        self.key_node_offset
+    }
+}
+
+impl FastLeafElement {
+    pub fn key_node_offset(&self) -> u32 {
+        self.key_node_offset
+    }
+
+    pub fn name_hint(&self) -> &[u8] {
+        &self.name_hint
+    }
+}
+
+impl HashLeafElement {
+    pub fn key_node_offset(&self) -> u32 {
+        self.key_node_offset
+    }
+
+    pub fn name_hash(&self) -> u32 {
+        self.name_hash
+    }
+}
+
+impl IndexRootElement {
+    pub fn subkeys_list_offset(&self) -> u32 {
+        self.subkeys_list_offset
+    }
 }
 
 impl NamedKey {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        ensure_remaining(buf, NAMED_KEY_SIZE, HiveStructure::NamedKey, offset)?;
         let flags = buf.get_u16_le();
         let last_written_timestamp = buf.get_u64_le();
         let access_bits = buf.get_u32_le();
@@ -444,7 +1319,13 @@ impl NamedKey {
         let work_var = buf.get_u32_le();
         let key_name_length = buf.get_u16_le();
         let class_name_length = buf.get_u16_le();
-        let key_name = read_arr(buf, key_name_length as usize);
+        ensure_remaining(
+            buf,
+            key_name_length as usize,
+            HiveStructure::NamedKey,
+            offset,
+        )?;
+        let key_name = buf.copy_to_bytes(key_name_length as usize);
 
         Ok(NamedKey {
             flags,
@@ -469,31 +1350,173 @@ impl NamedKey {
             key_name,
         })
     }
-}
 
-impl ValueKey {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
-        let name_length = buf.get_u16_le();
-        let data_size = buf.get_u32_le();
-        let data_offset = buf.get_u32_le();
-        let data_type = buf.get_u32_le();
-        let flags = buf.get_u16_le();
-        let spare = buf.get_u16_le();
-        let value_name = if name_length == 0 { "(default)".as_bytes().to_owned() } else {read_arr(buf, name_length as usize)};
-        Ok(ValueKey {
-            name_length,
-            data_size,
-            data_offset,
-            data_type,
-            flags,
-            spare,
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    pub fn last_written_timestamp(&self) -> FileTime {
+        FileTime::new(self.last_written_timestamp)
+    }
+
+    pub fn access_bits(&self) -> u32 {
+        self.access_bits
+    }
+
+    /// Returns true for the root key of the hive
+    pub fn is_root(&self) -> bool {
+        self.flags & KEY_HIVE_ENTRY != 0
+    }
+
+    pub fn parent_key_offset(&self) -> u32 {
+        self.parent_key_offset
+    }
+
+    pub fn number_of_subkeys(&self) -> u32 {
+        self.number_of_subkeys
+    }
+
+    pub fn number_of_volatile_subkeys(&self) -> u32 {
+        self.number_of_volatile_subkeys
+    }
+
+    pub fn subkeys_list_offset(&self) -> u32 {
+        self.subkeys_list_offset
+    }
+
+    pub fn volatile_subkeys_list_offset(&self) -> u32 {
+        self.volatile_subkeys_list_offset
+    }
+
+    pub fn number_of_key_values(&self) -> u32 {
+        self.number_of_key_values
+    }
+
+    pub fn key_values_list_offset(&self) -> u32 {
+        self.key_values_list_offset
+    }
+
+    pub fn key_security_offset(&self) -> u32 {
+        self.key_security_offset
+    }
+
+    pub fn class_name_offset(&self) -> u32 {
+        self.class_name_offset
+    }
+
+    pub fn largest_subkey_name_length(&self) -> u32 {
+        self.largest_subkey_name_length
+    }
+
+    pub fn largest_subkey_class_name_length(&self) -> u32 {
+        self.largest_subkey_class_name_length
+    }
+
+    pub fn largest_value_name_length(&self) -> u32 {
+        self.largest_value_name_length
+    }
+
+    pub fn largest_value_data_size(&self) -> u32 {
+        self.largest_value_data_size
+    }
+
+    pub fn work_var(&self) -> u32 {
+        self.work_var
+    }
+
+    pub fn key_name_length(&self) -> u16 {
+        self.key_name_length
+    }
+
+    pub fn class_name_length(&self) -> u16 {
+        self.class_name_length
+    }
+
+    /// Returns the decoded key name. ASCII names are borrowed from the hive without copying.
+    pub fn key_name(&self) -> Cow<'_, str> {
+        decode_name(&self.key_name, self.flags & KEY_COMP_NAME != 0)
+    }
+
+    /// Returns the key name as stored in the hive, before decoding
+    pub fn raw_key_name(&self) -> &Bytes {
+        &self.key_name
+    }
+}
+
+impl ValueKey {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        ensure_remaining(buf, VALUE_KEY_SIZE, HiveStructure::ValueKey, offset)?;
+        let name_length = buf.get_u16_le();
+        let data_size = buf.get_u32_le();
+        let data_offset = buf.get_u32_le();
+        let data_type = buf.get_u32_le();
+        let flags = buf.get_u16_le();
+        let spare = buf.get_u16_le();
+        ensure_remaining(buf, name_length as usize, HiveStructure::ValueKey, offset)?;
+        let value_name = buf.copy_to_bytes(name_length as usize);
+        Ok(ValueKey {
+            name_length,
+            data_size,
+            data_offset,
+            data_type,
+            flags,
+            spare,
             value_name,
         })
     }
+
+    pub fn name_length(&self) -> u16 {
+        self.name_length
+    }
+
+    pub fn data_size(&self) -> u32 {
+        self.data_size
+    }
+
+    pub fn data_offset(&self) -> u32 {
+        self.data_offset
+    }
+
+    /// Returns true when the data is stored inline in the data offset field
+    pub fn is_data_resident(&self) -> bool {
+        self.data_size & DATA_RESIDENT_FLAG != 0
+    }
+
+    /// Returns the size of the data, without the resident data flag
+    pub fn data_length(&self) -> u32 {
+        self.data_size & !DATA_RESIDENT_FLAG
+    }
+
+    pub fn data_type(&self) -> u32 {
+        self.data_type
+    }
+
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    pub fn spare(&self) -> u16 {
+        self.spare
+    }
+
+    /// Returns the decoded value name, or `(default)` for the unnamed default value. ASCII names
+    /// are borrowed from the hive without copying.
+    pub fn value_name(&self) -> Cow<'_, str> {
+        if self.value_name.is_empty() {
+            return Cow::Borrowed(DEFAULT_VALUE_NAME);
+        }
+        decode_name(&self.value_name, self.flags & VALUE_COMP_NAME != 0)
+    }
+
+    /// Returns the value name as stored in the hive, before decoding
+    pub fn raw_value_name(&self) -> &Bytes {
+        &self.value_name
+    }
 }
 
 impl SecurityKey {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        ensure_remaining(buf, SECURITY_KEY_SIZE, HiveStructure::SecurityKey, offset)?;
         // Skip unknown 2 bytes
         buf.advance(2);
 
@@ -501,7 +1524,13 @@ impl SecurityKey {
         let next_security_key_offset = buf.get_u32_le();
         let reference_count = buf.get_u32_le();
         let nt_security_descriptor_size = buf.get_u32_le();
-        let nt_security_descriptor = read_arr(buf, nt_security_descriptor_size as usize);
+        ensure_remaining(
+            buf,
+            nt_security_descriptor_size as usize,
+            HiveStructure::SecurityKey,
+            offset,
+        )?;
+        let nt_security_descriptor = buf.copy_to_bytes(nt_security_descriptor_size as usize);
 
         Ok(SecurityKey {
             previous_security_key_offset,
@@ -511,28 +1540,1080 @@ impl SecurityKey {
             nt_security_descriptor,
         })
     }
+
+    pub fn previous_security_key_offset(&self) -> u32 {
+        self.previous_security_key_offset
+    }
+
+    pub fn next_security_key_offset(&self) -> u32 {
+        self.next_security_key_offset
+    }
+
+    pub fn reference_count(&self) -> u32 {
+        self.reference_count
+    }
+
+    pub fn nt_security_descriptor_size(&self) -> u32 {
+        self.nt_security_descriptor_size
+    }
+
+    pub fn nt_security_descriptor(&self) -> &Bytes {
+        &self.nt_security_descriptor
+    }
 }
 
 impl DataBlock {
-    fn build(buf: &mut impl Buf) -> Result<Self, HiveParseError> {
+    fn build(buf: &mut impl Buf, offset: u64) -> Result<Self, HiveParseError> {
+        ensure_remaining(buf, DATA_BLOCK_SIZE, HiveStructure::DataBlock, offset)?;
         let number_of_segments = buf.get_u16_le();
         let data_block_list_offset = buf.get_u32_le();
-        let _padding = buf.get_u32_le();
         Ok(DataBlock {
             number_of_segments,
             data_block_list_offset,
         })
     }
+
+    pub fn number_of_segments(&self) -> u16 {
+        self.number_of_segments
+    }
+
+    pub fn data_block_list_offset(&self) -> u32 {
+        self.data_block_list_offset
+    }
+}
+
+/// Navigable view over a parsed hive, resolving cell offsets into keys and values
+#[derive(Debug)]
+pub struct Hive {
+    primary_file: HivePrimaryFile,
+    // Transaction logs which could not be parsed while recovering a dirty hive
+    skipped_logs: Vec<(PathBuf, RecoveryError)>,
+}
+
+/// A key of a hive. The nk cell is borrowed from the hive, or owned when the hive was parsed in
+/// [`ParseMode::Lazy`].
+#[derive(Debug, Clone)]
+pub struct Key<'a> {
+    hive: &'a Hive,
+    offset: u32,
+    named_key: Cow<'a, NamedKey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Value<'a> {
+    hive: &'a Hive,
+    offset: u32,
+    value_key: Cow<'a, ValueKey>,
+}
+
+impl Hive {
+    pub fn new(primary_file: HivePrimaryFile) -> Self {
+        Hive {
+            primary_file,
+            skipped_logs: vec![],
+        }
+    }
+
+    /// Opens and parses the hive file at the given path. The file is memory-mapped so that large
+    /// hives are not copied onto the heap, falling back to reading it into memory when it can not
+    /// be mapped.
+    pub fn open(path: impl AsRef<Path>, pool: &ThreadPool) -> Result<Self, HiveOpenError> {
+        Hive::open_with_options(path, pool, &ParseOptions::default())
+    }
+
+    /// Opens and parses the hive file at the given path. With [`ParseOptions::with_replay_logs`],
+    /// a dirty hive is first recovered from the .LOG1 and .LOG2 files stored next to it.
+    pub fn open_with_options(
+        path: impl AsRef<Path>,
+        pool: &ThreadPool,
+        options: &ParseOptions,
+    ) -> Result<Self, HiveOpenError> {
+        let path = path.as_ref();
+        let mut bytes = read_file(path)?;
+        let mut skipped_logs = vec![];
+        if options.replay_logs() {
+            let log_paths = recovery::find_transaction_logs(path);
+            let logs = log_paths
+                .iter()
+                .map(|log_path| read_file(log_path))
+                .collect::<io::Result<Vec<_>>>()?;
+            let replayed = recovery::replay(bytes, &logs)?;
+            skipped_logs = replayed
+                .skipped_logs()
+                .iter()
+                .map(|(index, error)| (log_paths[*index].clone(), error.clone()))
+                .collect();
+            bytes = replayed.into_hive();
+        }
+        Ok(Hive {
+            primary_file: parse_registry_with_options(bytes, pool, options)?,
+            skipped_logs,
+        })
+    }
+
+    /// Parses a hive from a stream, reading cells on demand through a small page cache instead
+    /// of loading the whole hive into memory
+    pub fn from_reader(reader: impl Read + Seek + Send + 'static) -> Result<Self, HiveOpenError> {
+        Ok(Hive::new(parse_registry_from_reader(
+            reader,
+            &ParseOptions::default(),
+        )?))
+    }
+
+    pub fn primary_file(&self) -> &HivePrimaryFile {
+        &self.primary_file
+    }
+
+    /// Returns the transaction logs which could not be parsed and were left out while recovering
+    /// a dirty hive, along with the reason
+    pub fn skipped_logs(&self) -> &[(PathBuf, RecoveryError)] {
+        &self.skipped_logs
+    }
+
+    /// Returns the base block checksum mismatch of a hive parsed in [`ChecksumMode::Lenient`],
+    /// or `None` when the checksum is valid
+    pub fn checksum_error(&self) -> Option<HiveParseError> {
+        self.primary_file.base_block().checksum_error()
+    }
+
+    pub fn root(&self) -> Result<Key<'_>, HiveParseError> {
+        self.key(self.primary_file.base_block().root_key_offset())
+    }
+
+    /// Opens the key at the given path relative to the root key, e.g.
+    /// `Microsoft\\Windows\\CurrentVersion\\Run`. Key names are compared case-insensitively,
+    /// and the name hashes and hints stored in lh and lf lists are used to skip non-matching
+    /// subkeys without decoding their nk cells.
+    pub fn open_key(&self, path: &str) -> Result<Option<Key<'_>>, HiveParseError> {
+        let mut key = self.root()?;
+        for name in path
+            .split(KEY_PATH_SEPARATOR)
+            .filter(|name| !name.is_empty())
+        {
+            match key.subkey(name)? {
+                Some(subkey) => key = subkey,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    /// Returns the key whose allocated nk cell starts at the given offset
+    pub fn key(&self, offset: u32) -> Result<Key<'_>, HiveParseError> {
+        let named_key = match self.primary_file.cell_data(offset)? {
+            Some(Cow::Borrowed(CellData::NamedKey(named_key))) => Cow::Borrowed(named_key),
+            Some(Cow::Owned(CellData::NamedKey(named_key))) => Cow::Owned(named_key),
+            _ => {
+                return Err(HiveParseError::InvalidCell {
+                    structure: HiveStructure::NamedKey,
+                    offset: file_offset(offset),
+                })
+            }
+        };
+        Ok(Key {
+            hive: self,
+            offset,
+            named_key,
+        })
+    }
+
+    fn value(&self, offset: u32) -> Result<Value<'_>, HiveParseError> {
+        let value_key = match self.primary_file.cell_data(offset)? {
+            Some(Cow::Borrowed(CellData::ValueKey(value_key))) => Cow::Borrowed(value_key),
+            Some(Cow::Owned(CellData::ValueKey(value_key))) => Cow::Owned(value_key),
+            _ => {
+                return Err(HiveParseError::InvalidCell {
+                    structure: HiveStructure::ValueKey,
+                    offset: file_offset(offset),
+                })
+            }
+        };
+        Ok(Value {
+            hive: self,
+            offset,
+            value_key,
+        })
+    }
+
+    fn security_descriptor(&self, offset: u32) -> Result<SecurityDescriptor, HiveParseError> {
+        match self.primary_file.cell_data(offset)?.as_deref() {
+            Some(CellData::SecurityKey(security_key)) => SecurityDescriptor::parse(
+                security_key.nt_security_descriptor(),
+                file_offset(offset) + (CELL_SIZE_LENGTH + CELL_SIG_SIZE + SECURITY_KEY_SIZE) as u64,
+            ),
+            _ => Err(HiveParseError::InvalidCell {
+                structure: HiveStructure::SecurityKey,
+                offset: file_offset(offset),
+            }),
+        }
+    }
+
+    fn subkey_offsets(
+        &self,
+        list_offset: u32,
+        offsets: &mut Vec<u32>,
+    ) -> Result<(), HiveParseError> {
+        match self.primary_file.cell_data(list_offset)?.as_deref() {
+            Some(CellData::IndexRoot(index_root)) => {
+                // An index root only ever points to leaf lists, never to another index root.
+                // Each leaf may only be referenced once, otherwise a small hostile hive could
+                // expand into an arbitrarily large list of subkeys.
+                let mut visited = HashSet::new();
+                for element in index_root.elements() {
+                    let leaf_offset = element.subkeys_list_offset();
+                    if !visited.insert(leaf_offset) {
+                        return Err(HiveParseError::Malformed {
+                            structure: HiveStructure::IndexRoot,
+                            offset: file_offset(list_offset),
+                            msg: "leaf list referenced more than once",
+                        });
+                    }
+                    self.leaf_offsets(leaf_offset, offsets)?;
+                }
+                Ok(())
+            }
+            _ => self.leaf_offsets(list_offset, offsets),
+        }
+    }
+
+    fn leaf_offsets(&self, list_offset: u32, offsets: &mut Vec<u32>) -> Result<(), HiveParseError> {
+        match self.primary_file.cell_data(list_offset)?.as_deref() {
+            Some(CellData::IndexLeaf(leaf)) => offsets.extend(
+                leaf.elements()
+                    .iter()
+                    .map(IndexLeafElement::key_node_offset),
+            ),
+            Some(CellData::FastLeaf(leaf)) => {
+                offsets.extend(leaf.elements().iter().map(FastLeafElement::key_node_offset))
+            }
+            Some(CellData::HashLeaf(leaf)) => {
+                offsets.extend(leaf.elements().iter().map(HashLeafElement::key_node_offset))
+            }
+            _ => {
+                return Err(HiveParseError::InvalidCell {
+                    structure: HiveStructure::SubkeyList,
+                    offset: file_offset(list_offset),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn find_subkey(&self, list_offset: u32, name: &str) -> Result<Option<Key<'_>>, HiveParseError> {
+        match self.primary_file.cell_data(list_offset)?.as_deref() {
+            Some(CellData::IndexRoot(index_root)) => {
+                let mut visited = HashSet::new();
+                for element in index_root.elements() {
+                    let leaf_offset = element.subkeys_list_offset();
+                    if !visited.insert(leaf_offset) {
+                        return Err(HiveParseError::Malformed {
+                            structure: HiveStructure::IndexRoot,
+                            offset: file_offset(list_offset),
+                            msg: "leaf list referenced more than once",
+                        });
+                    }
+                    if let Some(key) = self.find_subkey_in_leaf(leaf_offset, name)? {
+                        return Ok(Some(key));
+                    }
+                }
+                Ok(None)
+            }
+            _ => self.find_subkey_in_leaf(list_offset, name),
+        }
+    }
+
+    fn find_subkey_in_leaf(
+        &self,
+        list_offset: u32,
+        name: &str,
+    ) -> Result<Option<Key<'_>>, HiveParseError> {
+        let candidates: Vec<u32> = match self.primary_file.cell_data(list_offset)?.as_deref() {
+            Some(CellData::IndexLeaf(leaf)) => leaf
+                .elements()
+                .iter()
+                .map(IndexLeafElement::key_node_offset)
+                .collect(),
+            Some(CellData::FastLeaf(leaf)) => leaf
+                .elements()
+                .iter()
+                .filter(|element| name_hint_matches(element.name_hint(), name))
+                .map(FastLeafElement::key_node_offset)
+                .collect(),
+            Some(CellData::HashLeaf(leaf)) => {
+                let hash = name_hash(name);
+                leaf.elements()
+                    .iter()
+                    .filter(|element| element.name_hash() == hash)
+                    .map(HashLeafElement::key_node_offset)
+                    .collect()
+            }
+            _ => {
+                return Err(HiveParseError::InvalidCell {
+                    structure: HiveStructure::SubkeyList,
+                    offset: file_offset(list_offset),
+                })
+            }
+        };
+        for offset in candidates {
+            let key = self.key(offset)?;
+            if names_equal(&key.name(), name) {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    fn cell_bytes(&self, offset: u32, structure: HiveStructure) -> Result<Bytes, HiveParseError> {
+        self.primary_file
+            .cell_bytes(offset)?
+            .ok_or(HiveParseError::InvalidCell {
+                structure,
+                offset: file_offset(offset),
+            })
+    }
+
+    /// Reads a list of cell offsets, as used by value lists and big data segment lists
+    fn offset_list(
+        &self,
+        offset: u32,
+        count: usize,
+        structure: HiveStructure,
+    ) -> Result<Bytes, HiveParseError> {
+        let list = self.cell_bytes(offset, structure)?;
+        ensure_remaining(
+            &list,
+            count.saturating_mul(4),
+            structure,
+            file_offset(offset) + CELL_SIZE_LENGTH as u64,
+        )?;
+        Ok(list)
+    }
+
+    /// Reads the data of a value key. The value key does not have to be allocated, which allows
+    /// reading the data of deleted values as long as their data cells were not reused.
+    pub fn value_data(
+        &self,
+        value_key: &ValueKey,
+        value_offset: u32,
+    ) -> Result<Bytes, HiveParseError> {
+        let data_size = value_key.data_length() as usize;
+        let data_offset = value_key.data_offset();
+        if value_key.is_data_resident() {
+            if data_size > DATA_RESIDENT_MAX_SIZE {
+                return Err(HiveParseError::Malformed {
+                    structure: HiveStructure::ValueKey,
+                    offset: file_offset(value_offset) + CELL_SIZE_LENGTH as u64,
+                    msg: "resident data larger than 4 bytes",
+                });
+            }
+            return Ok(Bytes::copy_from_slice(
+                &data_offset.to_le_bytes()[..data_size],
+            ));
+        }
+        if data_size == 0 {
+            return Ok(Bytes::new());
+        }
+        if data_size > BIG_DATA_SEGMENT_SIZE {
+            if let Ok(CellData::DataBlock(data_block)) = self.primary_file.decode_cell(data_offset)
+            {
+                return self.big_data(&data_block, data_size);
+            }
+        }
+        let data = self.cell_bytes(data_offset, HiveStructure::ValueData)?;
+        ensure_remaining(
+            &data,
+            data_size,
+            HiveStructure::ValueData,
+            file_offset(data_offset) + CELL_SIZE_LENGTH as u64,
+        )?;
+        Ok(data.slice(..data_size))
+    }
+
+    /// Reassembles value data stored in the segments of a big data block
+    fn big_data(&self, data_block: &DataBlock, data_size: usize) -> Result<Bytes, HiveParseError> {
+        let number_of_segments = data_block.number_of_segments() as usize;
+        let mut list = self.offset_list(
+            data_block.data_block_list_offset(),
+            number_of_segments,
+            HiveStructure::DataBlock,
+        )?;
+
+        // Segments may only be referenced once, which bounds the reassembled data by the size
+        // of the hive rather than by the attacker controlled data size
+        let mut visited = HashSet::new();
+        let mut data = BytesMut::new();
+        for _ in 0..number_of_segments {
+            let segment_offset = list.get_u32_le();
+            if !visited.insert(segment_offset) {
+                return Err(HiveParseError::Malformed {
+                    structure: HiveStructure::DataBlock,
+                    offset: file_offset(data_block.data_block_list_offset()),
+                    msg: "segment referenced more than once",
+                });
+            }
+            let segment = self.cell_bytes(segment_offset, HiveStructure::ValueData)?;
+            let length = segment
+                .len()
+                .min(BIG_DATA_SEGMENT_SIZE)
+                .min(data_size - data.len());
+            data.extend_from_slice(&segment[..length]);
+        }
+        if data.len() < data_size {
+            return Err(HiveParseError::Truncated {
+                structure: HiveStructure::ValueData,
+                offset: file_offset(data_block.data_block_list_offset()),
+                needed: data_size,
+                available: data.len(),
+            });
+        }
+        Ok(data.freeze())
+    }
+}
+
+impl From<HivePrimaryFile> for Hive {
+    fn from(primary_file: HivePrimaryFile) -> Self {
+        Hive::new(primary_file)
+    }
+}
+
+impl<'a> Key<'a> {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn named_key(&self) -> &NamedKey {
+        &self.named_key
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        self.named_key.key_name()
+    }
+
+    /// Returns the path of the key relative to the root key, which itself has an empty path.
+    /// When a parent can not be resolved, e.g. for keys recovered from unallocated cells, the
+    /// path starts at the last parent found.
+    pub fn path(&self) -> String {
+        if self.named_key.is_root() {
+            return String::new();
+        }
+        let mut names = vec![self.name().into_owned()];
+        let mut key = self.clone();
+        while let Some(parent) = key.parent() {
+            // The root key is not part of the path
+            if parent.named_key.is_root() || names.len() >= MAX_KEY_DEPTH {
+                break;
+            }
+            names.push(parent.name().into_owned());
+            key = parent;
+        }
+        names.reverse();
+        names.join("\\")
+    }
+
+    pub fn last_written_timestamp(&self) -> FileTime {
+        self.named_key.last_written_timestamp()
+    }
+
+    /// Returns the parent key, or `None` for the root key of the hive
+    pub fn parent(&self) -> Option<Key<'a>> {
+        if self.named_key.is_root() {
+            return None;
+        }
+        self.hive.key(self.named_key.parent_key_offset()).ok()
+    }
+
+    /// Returns the security descriptor of the sk cell the key refers to
+    pub fn security(&self) -> Result<SecurityDescriptor, HiveParseError> {
+        self.hive
+            .security_descriptor(self.named_key.key_security_offset())
+    }
+
+    /// Returns the class name of the key, or `None` when it has none
+    pub fn class_name(&self) -> Result<Option<String>, HiveParseError> {
+        let offset = self.named_key.class_name_offset();
+        let length = self.named_key.class_name_length() as usize;
+        if length == 0 || offset == CELL_OFFSET_NONE {
+            return Ok(None);
+        }
+        let structure = HiveStructure::ClassName;
+        let data = self.hive.cell_bytes(offset, structure)?;
+        ensure_remaining(
+            &data,
+            length,
+            structure,
+            file_offset(offset) + CELL_SIZE_LENGTH as u64,
+        )?;
+        Ok(Some(decode_utf16le(&data[..length])))
+    }
+
+    /// Returns the direct subkey with the given name, compared case-insensitively
+    pub fn subkey(&self, name: &str) -> Result<Option<Key<'a>>, HiveParseError> {
+        let list_offset = self.named_key.subkeys_list_offset();
+        if self.named_key.number_of_subkeys() == 0 || list_offset == CELL_OFFSET_NONE {
+            return Ok(None);
+        }
+        self.hive.find_subkey(list_offset, name)
+    }
+
+    pub fn subkeys(&self) -> Result<Vec<Key<'a>>, HiveParseError> {
+        let list_offset = self.named_key.subkeys_list_offset();
+        if self.named_key.number_of_subkeys() == 0 || list_offset == CELL_OFFSET_NONE {
+            return Ok(vec![]);
+        }
+        let mut offsets = vec![];
+        self.hive.subkey_offsets(list_offset, &mut offsets)?;
+        offsets
+            .into_iter()
+            .map(|offset| self.hive.key(offset))
+            .collect()
+    }
+
+    pub fn values(&self) -> Result<Vec<Value<'a>>, HiveParseError> {
+        let list_offset = self.named_key.key_values_list_offset();
+        let number_of_values = self.named_key.number_of_key_values() as usize;
+        if number_of_values == 0 || list_offset == CELL_OFFSET_NONE {
+            return Ok(vec![]);
+        }
+        let mut list =
+            self.hive
+                .offset_list(list_offset, number_of_values, HiveStructure::ValueList)?;
+        (0..number_of_values)
+            .map(|_| self.hive.value(list.get_u32_le()))
+            .collect()
+    }
+
+    /// Returns the values of the key which can be read, along with the errors reading the value
+    /// list or the values which could not be. Unlike [`Key::values`], one unreadable value does
+    /// not hide the others.
+    pub fn readable_values(&self) -> (Vec<Value<'a>>, Vec<HiveParseError>) {
+        let list_offset = self.named_key.key_values_list_offset();
+        let number_of_values = self.named_key.number_of_key_values() as usize;
+        if number_of_values == 0 || list_offset == CELL_OFFSET_NONE {
+            return (vec![], vec![]);
+        }
+        let mut list =
+            match self
+                .hive
+                .offset_list(list_offset, number_of_values, HiveStructure::ValueList)
+            {
+                Ok(list) => list,
+                Err(e) => return (vec![], vec![e]),
+            };
+        let mut values = vec![];
+        let mut errors = vec![];
+        for _ in 0..number_of_values {
+            match self.hive.value(list.get_u32_le()) {
+                Ok(value) => values.push(value),
+                Err(e) => errors.push(e),
+            }
+        }
+        (values, errors)
+    }
+}
+
+impl<'a> Value<'a> {
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn value_key(&self) -> &ValueKey {
+        &self.value_key
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        self.value_key.value_name()
+    }
+
+    pub fn data_type(&self) -> u32 {
+        self.value_key.data_type()
+    }
+
+    /// Returns the raw bytes of the value data, whether it is stored inline in the value key,
+    /// in a single data cell or split across the segments of a big data block
+    pub fn data(&self) -> Result<Bytes, HiveParseError> {
+        self.hive.value_data(&self.value_key, self.offset)
+    }
+
+    /// Returns the value data decoded according to its data type
+    pub fn reg_value(&self) -> Result<RegValue, HiveParseError> {
+        Ok(RegValue::decode(self.data_type(), self.data()?))
+    }
+}
+
+/// Registry value data decoded according to its data type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegValue {
+    None(Bytes),
+    Sz(String),
+    ExpandSz(String),
+    Binary(Bytes),
+    Dword(u32),
+    DwordBigEndian(u32),
+    Link(String),
+    MultiSz(Vec<String>),
+    Qword(u64),
+    /// Any other data type, including malformed fixed-size values, kept as raw bytes
+    Unknown(u32, Bytes),
+}
+
+impl RegValue {
+    pub fn decode(data_type: u32, data: Bytes) -> Self {
+        match data_type {
+            REG_NONE => RegValue::None(data),
+            REG_SZ => RegValue::Sz(decode_utf16le_string(&data)),
+            REG_EXPAND_SZ => RegValue::ExpandSz(decode_utf16le_string(&data)),
+            REG_BINARY => RegValue::Binary(data),
+            REG_DWORD if data.len() >= 4 => RegValue::Dword((&data[..]).get_u32_le()),
+            REG_DWORD_BIG_ENDIAN if data.len() >= 4 => {
+                RegValue::DwordBigEndian((&data[..]).get_u32())
+            }
+            // Symbolic links are stored without a terminating null character
+            REG_LINK => RegValue::Link(decode_utf16le(&data)),
+            REG_MULTI_SZ => RegValue::MultiSz(decode_utf16le_multi_string(&data)),
+            REG_QWORD if data.len() >= 8 => RegValue::Qword((&data[..]).get_u64_le()),
+            data_type => RegValue::Unknown(data_type, data),
+        }
+    }
+
+    pub fn data_type(&self) -> u32 {
+        match self {
+            RegValue::None(_) => REG_NONE,
+            RegValue::Sz(_) => REG_SZ,
+            RegValue::ExpandSz(_) => REG_EXPAND_SZ,
+            RegValue::Binary(_) => REG_BINARY,
+            RegValue::Dword(_) => REG_DWORD,
+            RegValue::DwordBigEndian(_) => REG_DWORD_BIG_ENDIAN,
+            RegValue::Link(_) => REG_LINK,
+            RegValue::MultiSz(_) => REG_MULTI_SZ,
+            RegValue::Qword(_) => REG_QWORD,
+            RegValue::Unknown(data_type, _) => *data_type,
+        }
+    }
+}
+
+impl fmt::Display for RegValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegValue::Sz(s) | RegValue::ExpandSz(s) | RegValue::Link(s) => write!(f, "{}", s),
+            RegValue::MultiSz(strings) => write!(f, "{}", strings.join("\\0")),
+            RegValue::Dword(n) | RegValue::DwordBigEndian(n) => write!(f, "0x{:08x} ({})", n, n),
+            RegValue::Qword(n) => write!(f, "0x{:016x} ({})", n, n),
+            RegValue::None(data) | RegValue::Binary(data) | RegValue::Unknown(_, data) => {
+                let hex: Vec<String> = data.iter().map(|b| format!("{:02x}", b)).collect();
+                write!(f, "{}", hex.join(" "))
+            }
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    pub fn with_checksum_mode(mut self, checksum_mode: ChecksumMode) -> Self {
+        self.checksum_mode = checksum_mode;
+        self
+    }
+
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.checksum_mode
+    }
+
+    pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Recover dirty hives from their transaction logs when opening them from a path
+    pub fn with_replay_logs(mut self, replay_logs: bool) -> Self {
+        self.replay_logs = replay_logs;
+        self
+    }
+
+    pub fn replay_logs(&self) -> bool {
+        self.replay_logs
+    }
+}
+
+/// Returns the name of a data type, e.g. `REG_SZ`, or `None` for types Windows does not define
+pub fn data_type_name(data_type: u32) -> Option<&'static str> {
+    DATA_TYPE_NAMES
+        .iter()
+        .find(|(known_type, _)| *known_type == data_type)
+        .map(|(_, name)| *name)
+}
+
+/// Returns the name of the data type, or its hexadecimal number when the type is unknown
+pub fn format_data_type(data_type: u32) -> String {
+    data_type_name(data_type).map_or_else(|| format!("{:#x}", data_type), str::to_string)
+}
+
+/// Parses a data type from its name, with or without the `REG_` prefix and ignoring case, or
+/// from its decimal or `0x` prefixed hexadecimal number
+pub fn parse_data_type(name: &str) -> Option<u32> {
+    let upper = name.trim().to_uppercase();
+    let unprefixed = upper.strip_prefix("REG_").unwrap_or(&upper);
+    if let Some((data_type, _)) = DATA_TYPE_NAMES
+        .iter()
+        .find(|(_, known_name)| known_name[4..] == *unprefixed)
+    {
+        return Some(*data_type);
+    }
+    match upper.strip_prefix("0X") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => upper.parse().ok(),
+    }
 }
 
 pub fn parse_registry(bytes: Bytes, pool: &ThreadPool) -> Result<HivePrimaryFile, HiveParseError> {
-    pool.install(|| HivePrimaryFile::build(bytes))
+    parse_registry_with_options(bytes, pool, &ParseOptions::default())
+}
+
+pub fn parse_registry_with_options(
+    bytes: Bytes,
+    pool: &ThreadPool,
+    options: &ParseOptions,
+) -> Result<HivePrimaryFile, HiveParseError> {
+    pool.install(|| HivePrimaryFile::build(bytes, options))
+}
+
+/// Parses a hive from any seekable stream, such as an archive entry or a forensic image. The
+/// base block is read immediately, cells are read and decoded when navigation reaches them.
+pub fn parse_registry_from_reader(
+    reader: impl Read + Seek + Send + 'static,
+    options: &ParseOptions,
+) -> Result<HivePrimaryFile, HiveOpenError> {
+    HivePrimaryFile::build_from_reader(Box::new(reader), options)
+}
+
+/// Maps the file into memory, or reads it when mapping fails, e.g. for empty files or pipes
+fn read_file(path: &Path) -> io::Result<Bytes> {
+    let file = File::open(path)?;
+    // Safety: the mapping is only ever read. As with any memory-mapped file, modifying or
+    // truncating the file while it is mapped is undefined behaviour, hives are expected to be
+    // collected copies which are not written to while they are parsed.
+    match unsafe { Mmap::map(&file) } {
+        Ok(mmap) => Ok(Bytes::from_owner(mmap)),
+        Err(_) => Ok(Bytes::from(fs::read(path)?)),
+    }
+}
+
+/// Decodes a key or value name, stored as Latin-1 when compressed and as UTF-16LE otherwise
+fn decode_name(name: &[u8], compressed: bool) -> Cow<'_, str> {
+    if compressed {
+        if name.is_ascii() {
+            // ASCII is valid UTF-8, so the name can be borrowed as is
+            return Cow::Borrowed(std::str::from_utf8(name).unwrap_or_default());
+        }
+        Cow::Owned(name.iter().map(|&b| b as char).collect())
+    } else {
+        Cow::Owned(decode_utf16le(name))
+    }
+}
+
+/// Decodes UTF-16LE data up to the first null character
+fn decode_utf16le_string(data: &[u8]) -> String {
+    let end = data
+        .chunks_exact(2)
+        .position(|unit| unit == [0, 0])
+        .map_or(data.len(), |position| position * 2);
+    decode_utf16le(&data[..end])
+}
+
+/// Decodes a sequence of null terminated UTF-16LE strings, ending at the first empty string
+fn decode_utf16le_multi_string(data: &[u8]) -> Vec<String> {
+    let mut strings = vec![];
+    let mut start = 0;
+    for (index, unit) in data.chunks_exact(2).enumerate() {
+        if unit == [0, 0] {
+            if index * 2 == start {
+                return strings;
+            }
+            strings.push(decode_utf16le(&data[start..index * 2]));
+            start = index * 2 + 2;
+        }
+    }
+    // The last string may be missing its terminating null character
+    if start < data.len() & !1 {
+        strings.push(decode_utf16le(&data[start..]));
+    }
+    strings
+}
+
+fn decode_utf16le(data: &[u8]) -> String {
+    // Drop a trailing odd byte, which can not be part of a UTF-16 code unit
+    let data = &data[..data.len() & !1];
+    match WStr::from_utf16le(data) {
+        Ok(wstr) => wstr.to_utf8(),
+        Err(_) => {
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+    }
+}
+
+/// Upcases a single UTF-16 code unit the way Windows compares key names: each code unit maps to
+/// exactly one code unit, so characters without a simple uppercase mapping (such as ß) and
+/// surrogates are left unchanged
+pub(crate) fn upcase(unit: u16) -> u16 {
+    let Some(c) = char::from_u32(unit as u32) else {
+        return unit;
+    };
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if (u as u32) <= u16::MAX as u32 => u as u16,
+        _ => unit,
+    }
 }
 
-fn read_arr(buf: &mut impl Buf, length: usize) -> Vec<u8> {
-    let mut result = Vec::with_capacity(length);
-    for _ in 0..length {
-        result.push(buf.get_u8());
+/// Compares two key names case-insensitively
+pub(crate) fn names_equal(a: &str, b: &str) -> bool {
+    a.encode_utf16()
+        .map(upcase)
+        .eq(b.encode_utf16().map(upcase))
+}
+
+/// Calculates the hash of a key name as stored in lh lists
+fn name_hash(name: &str) -> u32 {
+    name.encode_utf16().fold(0, |hash, unit| {
+        hash.wrapping_mul(NAME_HASH_MULTIPLIER)
+            .wrapping_add(upcase(unit) as u32)
+    })
+}
+
+/// Checks a key name against the hint stored in lf lists, which holds the first 4 characters of
+/// the name padded with null bytes. Only ASCII characters are compared, others are assumed to
+/// match.
+fn name_hint_matches(hint: &[u8], name: &str) -> bool {
+    let mut units = name.encode_utf16();
+    hint.iter()
+        .take(NAME_HINT_SIZE)
+        .all(|&hint_byte| match (units.next(), hint_byte) {
+            (None, hint_byte) => hint_byte == 0,
+            (Some(unit), hint_byte) if unit <= 0x7F && hint_byte <= 0x7F => {
+                upcase(unit) == upcase(hint_byte as u16)
+            }
+            _ => true,
+        })
+}
+
+/// Converts a cell offset, relative to the hive bins data, into an absolute file offset
+pub(crate) fn file_offset(cell_offset: u32) -> u64 {
+    HIVE_BASE_BLOCK_SIZE as u64 + cell_offset as u64
+}
+
+pub(crate) fn ensure_remaining(
+    buf: &impl Buf,
+    needed: usize,
+    structure: HiveStructure,
+    offset: u64,
+) -> Result<(), HiveParseError> {
+    if buf.remaining() < needed {
+        return Err(HiveParseError::Truncated {
+            structure,
+            offset,
+            needed,
+            available: buf.remaining(),
+        });
+    }
+    Ok(())
+}
+
+fn read_guid(buf: &mut impl Buf) -> Guid {
+    let mut bytes = [0; GUID_SIZE];
+    buf.copy_to_slice(&mut bytes);
+    Guid::from_bytes(bytes)
+}
+
+fn check_signature(
+    buf: &mut impl Buf,
+    signature: &'static str,
+    structure: HiveStructure,
+    offset: u64,
+) -> Result<(), HiveParseError> {
+    let found = buf.copy_to_bytes(signature.len());
+    if found != signature.as_bytes() {
+        return Err(HiveParseError::InvalidSignature {
+            structure,
+            offset,
+            expected: signature,
+            found: found.to_vec(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_hive::{
+        build_hive, named_key, value_key, HiveBuilder, TestKey, CELL_OFFSET_NONE,
+    };
+    use std::io::Cursor;
+
+    const BIN_SIZE: usize = 4096;
+
+    fn block_with_dwords(dwords: &[u32]) -> Vec<u8> {
+        let mut block = vec![0; HIVE_BASE_BLOCK_SIZE];
+        for (index, dword) in dwords.iter().enumerate() {
+            block[index * 4..index * 4 + 4].copy_from_slice(&dword.to_le_bytes());
+        }
+        block
+    }
+
+    #[test]
+    fn checksum_xors_the_first_508_bytes() {
+        let block = block_with_dwords(&[0x6667_6572, 0x0000_0001, 0x0000_0001]);
+        assert_eq!(HiveBaseBlock::calculate_checksum(&block), 0x6667_6572);
+
+        // The checksum field itself and the rest of the block are not covered
+        let mut block = block;
+        block[HIVE_BASE_BLOCK_CHECKSUM_OFFSET..].fill(0xFF);
+        assert_eq!(HiveBaseBlock::calculate_checksum(&block), 0x6667_6572);
+    }
+
+    #[test]
+    fn checksum_replaces_zero_and_all_bits_set() {
+        assert_eq!(
+            HiveBaseBlock::calculate_checksum(&block_with_dwords(&[])),
+            1
+        );
+        assert_eq!(
+            HiveBaseBlock::calculate_checksum(&block_with_dwords(&[0x1234_5678, 0x1234_5678])),
+            1
+        );
+        assert_eq!(
+            HiveBaseBlock::calculate_checksum(&block_with_dwords(&[0xFFFF_FFFF])),
+            0xFFFF_FFFE
+        );
+        assert_eq!(
+            HiveBaseBlock::calculate_checksum(&block_with_dwords(&[0xFFFF_0000, 0x0000_FFFF])),
+            0xFFFF_FFFE
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch_depends_on_mode() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let hive = build_hive(&TestKey::new("ROOT"));
+        let primary_file = parse_registry(hive.clone(), &pool).unwrap();
+        assert!(primary_file.base_block().checksum_error().is_none());
+
+        let mut corrupted = hive.to_vec();
+        corrupted[HIVE_BASE_BLOCK_CHECKSUM_OFFSET] ^= 1;
+        let corrupted = Bytes::from(corrupted);
+        let strict = ParseOptions::new().with_checksum_mode(ChecksumMode::Strict);
+        assert!(matches!(
+            parse_registry_with_options(corrupted.clone(), &pool, &strict),
+            Err(HiveParseError::InvalidChecksum { .. })
+        ));
+        let lenient = ParseOptions::new().with_checksum_mode(ChecksumMode::Lenient);
+        let primary_file = parse_registry_with_options(corrupted, &pool, &lenient).unwrap();
+        let Some(HiveParseError::InvalidChecksum {
+            stored, calculated, ..
+        }) = primary_file.base_block().checksum_error()
+        else {
+            panic!("checksum mismatch not reported");
+        };
+        assert_eq!(stored ^ calculated, 1);
+    }
+
+    /// Builds a hive with two bins, returning it along with the offsets of the root key and of a
+    /// cell at the end of the first bin whose size reaches into the second one
+    fn two_bin_hive() -> (Vec<u8>, u32, u32) {
+        let mut builder = HiveBuilder::new();
+        let root = builder.key(&TestKey::new("ROOT"), CELL_OFFSET_NONE);
+        let straddling = builder.allocated_cell(&[0; 8]);
+        builder.allocated_cell(&[0; 4000]);
+        let mut hive = builder.finish(root).to_vec();
+        let size = -((BIN_SIZE + 16 - straddling as usize) as i32);
+        let size_offset = HIVE_BASE_BLOCK_SIZE + straddling as usize;
+        hive[size_offset..size_offset + CELL_SIZE_LENGTH].copy_from_slice(&size.to_le_bytes());
+        (hive, root, straddling)
+    }
+
+    #[test]
+    fn cells_are_bounded_by_their_bin() {
+        let (hive, root, straddling) = two_bin_hive();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let memory = parse_registry(Bytes::from(hive.clone()), &pool).unwrap();
+        let stream = Hive::from_reader(Cursor::new(hive)).unwrap();
+        for primary_file in [&memory, stream.primary_file()] {
+            let data = &primary_file.hive_bins_data;
+            assert_eq!(data.bin_end(0).unwrap(), Some(BIN_SIZE));
+            assert_eq!(data.bin_end(BIN_SIZE + 1000).unwrap(), Some(2 * BIN_SIZE));
+            assert_eq!(data.bin_end(2 * BIN_SIZE).unwrap(), None);
+            assert!(primary_file.cell_bytes(root).unwrap().is_some());
+            assert_eq!(primary_file.cell_bytes(straddling).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn stream_bin_lookups_remember_misses() {
+        let (mut hive, _, _) = two_bin_hive();
+        hive[HIVE_BASE_BLOCK_SIZE + BIN_SIZE] = b'x';
+        let stream = Hive::from_reader(Cursor::new(hive)).unwrap();
+        let data = &stream.primary_file().hive_bins_data;
+        assert_eq!(data.bin_end(BIN_SIZE + 1000).unwrap(), None);
+        assert_eq!(data.bin_end(BIN_SIZE + 2000).unwrap(), None);
+        let HiveBinsData::Stream { ranges, .. } = data else {
+            panic!("hive not read from a stream");
+        };
+        let ranges: Vec<_> = ranges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(start, range)| (*start, range.end, range.is_bin))
+            .collect();
+        assert_eq!(
+            ranges,
+            [(0, BIN_SIZE, true), (BIN_SIZE, 2 * BIN_SIZE, false)]
+        );
+    }
+
+    #[test]
+    fn readable_values_skip_unreadable_values() {
+        let mut builder = HiveBuilder::new();
+        let value = builder.allocated_cell(&value_key("Readable", REG_DWORD, 0x8000_0004, 7));
+        let list: Vec<u8> = [value, 0x0FFF_FFF8]
+            .iter()
+            .flat_map(|offset| offset.to_le_bytes())
+            .collect();
+        let list = builder.allocated_cell(&list);
+        let root = builder.allocated_cell(&named_key(
+            "ROOT",
+            KEY_HIVE_ENTRY | KEY_COMP_NAME,
+            CELL_OFFSET_NONE,
+            (0, CELL_OFFSET_NONE),
+            (2, list),
+        ));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let hive = Hive::new(parse_registry(builder.finish(root), &pool).unwrap());
+        let root = hive.root().unwrap();
+        assert!(root.values().is_err());
+        let (values, errors) = root.readable_values();
+        let names: Vec<_> = values
+            .iter()
+            .map(|value| value.name().into_owned())
+            .collect();
+        assert_eq!(names, ["Readable"]);
+        assert_eq!(errors.len(), 1);
     }
-    result
 }
@@ -0,0 +1,138 @@
+use crate::hive::Hive;
+use std::fmt;
+
+const SYSTEM: &str = "SYSTEM";
+const SOFTWARE: &str = "SOFTWARE";
+const SAM: &str = "SAM";
+const SECURITY: &str = "SECURITY";
+const NTUSER: &str = "NTUSER";
+const USRCLASS: &str = "UsrClass";
+const AMCACHE: &str = "Amcache";
+const BCD: &str = "BCD";
+const UNKNOWN: &str = "unknown";
+
+const SYSTEM_MOUNT_POINT: &str = "HKEY_LOCAL_MACHINE\\SYSTEM";
+const SOFTWARE_MOUNT_POINT: &str = "HKEY_LOCAL_MACHINE\\SOFTWARE";
+const SAM_MOUNT_POINT: &str = "HKEY_LOCAL_MACHINE\\SAM";
+const SECURITY_MOUNT_POINT: &str = "HKEY_LOCAL_MACHINE\\SECURITY";
+const NTUSER_MOUNT_POINT: &str = "HKEY_CURRENT_USER";
+const USRCLASS_MOUNT_POINT: &str = "HKEY_CURRENT_USER\\Software\\Classes";
+const BCD_MOUNT_POINT: &str = "HKEY_LOCAL_MACHINE\\BCD00000000";
+
+// Extensions stripped from the embedded file name before matching, e.g. NTUSER.DAT
+const FILE_NAME_EXTENSIONS: [&str; 2] = [".DAT", ".HVE"];
+
+// Paths relative to the root key which together identify a hive. A kind may have several
+// alternatives, matching when all paths of one of them exist. More specific hives are listed
+// first, as e.g. a SOFTWARE hive also contains the Classes key found in UsrClass hives.
+const MARKER_PATHS: [(HiveKind, &[&str]); 10] = [
+    (HiveKind::Amcache, &["Root\\InventoryApplicationFile"]),
+    (HiveKind::Amcache, &["Root\\File"]),
+    (HiveKind::Bcd, &["Description", "Objects"]),
+    (HiveKind::Sam, &["SAM\\Domains"]),
+    (HiveKind::Security, &["Policy", "RXACT"]),
+    (HiveKind::System, &["Select", "MountedDevices"]),
+    (HiveKind::Software, &["Microsoft", "Classes", "Policies"]),
+    (HiveKind::NtUser, &["Control Panel", "Environment"]),
+    (HiveKind::NtUser, &["Software\\Microsoft", "AppEvents"]),
+    (HiveKind::UsrClass, &["Local Settings"]),
+];
+
+/// The well-known hive a hive file was saved from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HiveKind {
+    System,
+    Software,
+    Sam,
+    Security,
+    NtUser,
+    UsrClass,
+    Amcache,
+    Bcd,
+    Unknown,
+}
+
+impl HiveKind {
+    /// Classifies a hive by the file name embedded in its base block, falling back to looking
+    /// for characteristic keys below the root key when the name is not recognized, as is the
+    /// case for hives saved with `reg save`.
+    pub fn detect(hive: &Hive) -> HiveKind {
+        let file_name = hive.primary_file().base_block().decoded_file_name();
+        HiveKind::from_file_name(&file_name)
+            .or_else(|| HiveKind::from_keys(hive))
+            .unwrap_or(HiveKind::Unknown)
+    }
+
+    /// Classifies a hive by the file name stored in its base block, e.g.
+    /// `\??\C:\Windows\System32\config\SOFTWARE`. Only the last path component is compared,
+    /// as the stored name is truncated to the last 31 characters of the path.
+    pub fn from_file_name(file_name: &str) -> Option<HiveKind> {
+        let name = file_name
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        let name = FILE_NAME_EXTENSIONS
+            .iter()
+            .find_map(|extension| name.strip_suffix(extension))
+            .unwrap_or(&name);
+        match name {
+            "SYSTEM" => Some(HiveKind::System),
+            "SOFTWARE" => Some(HiveKind::Software),
+            "SAM" => Some(HiveKind::Sam),
+            "SECURITY" => Some(HiveKind::Security),
+            "NTUSER" => Some(HiveKind::NtUser),
+            "USRCLASS" => Some(HiveKind::UsrClass),
+            "AMCACHE" => Some(HiveKind::Amcache),
+            "BCD" | "BCD-TEMPLATE" => Some(HiveKind::Bcd),
+            _ => None,
+        }
+    }
+
+    /// Classifies a hive by the keys found below its root key
+    pub fn from_keys(hive: &Hive) -> Option<HiveKind> {
+        MARKER_PATHS
+            .iter()
+            .find(|(_, paths)| {
+                paths
+                    .iter()
+                    .all(|path| matches!(hive.open_key(path), Ok(Some(_))))
+            })
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Returns where Windows loads the hive in the registry, e.g. `HKEY_LOCAL_MACHINE\\SOFTWARE`,
+    /// or `None` for hives which are not loaded at a fixed location
+    pub fn mount_point(&self) -> Option<&str> {
+        match self {
+            HiveKind::System => Some(SYSTEM_MOUNT_POINT),
+            HiveKind::Software => Some(SOFTWARE_MOUNT_POINT),
+            HiveKind::Sam => Some(SAM_MOUNT_POINT),
+            HiveKind::Security => Some(SECURITY_MOUNT_POINT),
+            HiveKind::NtUser => Some(NTUSER_MOUNT_POINT),
+            HiveKind::UsrClass => Some(USRCLASS_MOUNT_POINT),
+            HiveKind::Bcd => Some(BCD_MOUNT_POINT),
+            HiveKind::Amcache | HiveKind::Unknown => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            HiveKind::System => SYSTEM,
+            HiveKind::Software => SOFTWARE,
+            HiveKind::Sam => SAM,
+            HiveKind::Security => SECURITY,
+            HiveKind::NtUser => NTUSER,
+            HiveKind::UsrClass => USRCLASS,
+            HiveKind::Amcache => AMCACHE,
+            HiveKind::Bcd => BCD,
+            HiveKind::Unknown => UNKNOWN,
+        }
+    }
+}
+
+impl fmt::Display for HiveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
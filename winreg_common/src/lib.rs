@@ -1,2 +1,14 @@
+pub mod deleted;
+pub mod guid;
 pub mod hive;
+pub mod kind;
+pub mod pattern;
+pub mod recovery;
+pub mod regfile;
 pub mod root;
+pub mod security;
+mod stream;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_hive;
+pub mod timestamp;
+pub mod tree;
@@ -0,0 +1,492 @@
+use crate::hive::{self, Hive, HiveParseError, Key};
+use std::collections::HashSet;
+
+const PATH_SEPARATOR: char = '\\';
+const ANY_DEPTH: &str = "**";
+
+#[derive(Debug, Clone)]
+pub struct PatternParseError {
+    msg: String,
+}
+
+impl PatternParseError {
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// A glob pattern over key paths relative to the root key, e.g.
+/// `ControlSet*\\Services\\*\\Parameters`. Segments are separated by backslashes and support
+/// `*` for any characters within a segment, `?` for a single character and character classes
+/// such as `[a-z]` or `[!0-9]`. A segment consisting of `**` matches any number of segments,
+/// including none. Wildcard characters can be matched literally with a class, e.g. `[*]`.
+/// Matching ignores case unless the pattern is made case-sensitive.
+#[derive(Debug, Clone)]
+pub struct KeyPathPattern {
+    segments: Vec<Segment>,
+    case_sensitive: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    AnyDepth,
+    Literal(String),
+    Wildcard(Vec<Token>),
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// The positions in the pattern's segments reached after matching a key path
+type States = Vec<usize>;
+
+impl KeyPathPattern {
+    pub fn parse(pattern: &str) -> Result<Self, PatternParseError> {
+        let segments = pattern
+            .split(PATH_SEPARATOR)
+            .filter(|segment| !segment.is_empty())
+            .map(parse_segment)
+            .collect::<Result<_, _>>()?;
+        Ok(KeyPathPattern {
+            segments,
+            case_sensitive: false,
+        })
+    }
+
+    /// Returns a pattern matching every key, including the root key
+    pub fn any() -> Self {
+        KeyPathPattern {
+            segments: vec![Segment::AnyDepth],
+            case_sensitive: false,
+        }
+    }
+
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// Returns true when the key path, relative to the root key, matches the pattern
+    pub fn matches(&self, path: &str) -> bool {
+        let mut states = self.initial_states();
+        for name in path.split(PATH_SEPARATOR).filter(|name| !name.is_empty()) {
+            states = self.step(&states, name);
+            if states.is_empty() {
+                return false;
+            }
+        }
+        self.is_match(&states)
+    }
+
+    /// Walks the keys of the hive matching the pattern, in the order of the subkey lists.
+    /// Only subkeys which can still lead to a match are descended into, and segments without
+    /// wildcards are looked up through the hashes of the subkey lists instead of reading every
+    /// subkey.
+    pub fn matching_keys<'p, 'a>(&'p self, hive: &'a Hive) -> MatchingKeys<'p, 'a> {
+        let pending = match hive.root() {
+            Ok(root) => vec![Pending::Key(root, self.initial_states())],
+            Err(e) => vec![Pending::Error(e)],
+        };
+        MatchingKeys {
+            pattern: self,
+            pending,
+            visited: HashSet::new(),
+        }
+    }
+
+    fn initial_states(&self) -> States {
+        self.closure(vec![0])
+    }
+
+    /// Adds the positions reachable by letting `**` segments match no segment
+    fn closure(&self, mut states: States) -> States {
+        let mut index = 0;
+        while index < states.len() {
+            let position = states[index];
+            if let Some(Segment::AnyDepth) = self.segments.get(position) {
+                if !states.contains(&(position + 1)) {
+                    states.push(position + 1);
+                }
+            }
+            index += 1;
+        }
+        states
+    }
+
+    fn step(&self, states: &[usize], name: &str) -> States {
+        let mut next = vec![];
+        for &position in states {
+            let reached = match self.segments.get(position) {
+                Some(Segment::AnyDepth) => Some(position),
+                Some(segment) if self.segment_matches(segment, name) => Some(position + 1),
+                _ => None,
+            };
+            if let Some(reached) = reached.filter(|reached| !next.contains(reached)) {
+                next.push(reached);
+            }
+        }
+        self.closure(next)
+    }
+
+    fn is_match(&self, states: &[usize]) -> bool {
+        states.contains(&self.segments.len())
+    }
+
+    /// Returns the names to look up when every active segment is a literal, in which case the
+    /// subkeys do not need to be enumerated
+    fn literal_names<'p>(&'p self, states: &[usize]) -> Option<Vec<&'p str>> {
+        let mut names: Vec<&str> = vec![];
+        for &position in states {
+            match self.segments.get(position) {
+                Some(Segment::Literal(name))
+                    if !names.iter().any(|known| names_equal(known, name, false)) =>
+                {
+                    names.push(name)
+                }
+                Some(Segment::Literal(_)) => {}
+                Some(_) => return None,
+                None => {}
+            }
+        }
+        Some(names)
+    }
+
+    fn segment_matches(&self, segment: &Segment, name: &str) -> bool {
+        match segment {
+            Segment::AnyDepth => true,
+            Segment::Literal(literal) => names_equal(literal, name, self.case_sensitive),
+            Segment::Wildcard(tokens) => {
+                let name: Vec<char> = name.chars().collect();
+                tokens_match(tokens, &name, self.case_sensitive)
+            }
+        }
+    }
+}
+
+impl TryFrom<&str> for KeyPathPattern {
+    type Error = PatternParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        KeyPathPattern::parse(value)
+    }
+}
+
+enum Pending<'a> {
+    Key(Key<'a>, States),
+    Error(HiveParseError),
+}
+
+/// Iterator over the keys matching a `KeyPathPattern`. Errors reading a key's subkeys are
+/// returned in place of the keys which could not be read, after which the walk continues.
+pub struct MatchingKeys<'p, 'a> {
+    pattern: &'p KeyPathPattern,
+    // Keys still to visit, the next one last
+    pending: Vec<Pending<'a>>,
+    visited: HashSet<u32>,
+}
+
+impl<'a> MatchingKeys<'_, 'a> {
+    fn descend(&mut self, key: &Key<'a>, states: &[usize]) {
+        let mut children = vec![];
+        match self.pattern.literal_names(states) {
+            Some(names) => {
+                for name in names {
+                    match key.subkey(name) {
+                        Ok(Some(subkey)) => children.push(Ok(subkey)),
+                        Ok(None) => {}
+                        Err(e) => children.push(Err(e)),
+                    }
+                }
+            }
+            None => match key.subkeys() {
+                Ok(subkeys) => children.extend(subkeys.into_iter().map(Ok)),
+                Err(e) => children.push(Err(e)),
+            },
+        }
+        for child in children.into_iter().rev() {
+            match child {
+                Ok(subkey) => {
+                    let next = self.pattern.step(states, &subkey.name());
+                    if !next.is_empty() {
+                        self.pending.push(Pending::Key(subkey, next));
+                    }
+                }
+                Err(e) => self.pending.push(Pending::Error(e)),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for MatchingKeys<'_, 'a> {
+    type Item = Result<Key<'a>, HiveParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(pending) = self.pending.pop() {
+            let (key, states) = match pending {
+                Pending::Key(key, states) => (key, states),
+                Pending::Error(e) => return Some(Err(e)),
+            };
+            // Guard against cycles in the subkey lists of corrupted hives
+            if !self.visited.insert(key.offset()) {
+                continue;
+            }
+            self.descend(&key, &states);
+            if self.pattern.is_match(&states) {
+                return Some(Ok(key));
+            }
+        }
+        None
+    }
+}
+
+fn parse_segment(segment: &str) -> Result<Segment, PatternParseError> {
+    if segment == ANY_DEPTH {
+        return Ok(Segment::AnyDepth);
+    }
+    let mut tokens = vec![];
+    let mut chars = segment.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '*' => Token::AnyChars,
+            '?' => Token::AnyChar,
+            '[' => {
+                let negated = chars.next_if(|c| *c == '!' || *c == '^').is_some();
+                let mut ranges = vec![];
+                // A closing bracket directly after the opening one is part of the class
+                let mut first = true;
+                loop {
+                    let start = match chars.next() {
+                        Some(']') if !first => break,
+                        Some(start) => start,
+                        None => {
+                            return Err(PatternParseError {
+                                msg: format!("Unclosed character class in '{}'", segment),
+                            })
+                        }
+                    };
+                    first = false;
+                    let end = match chars.next_if_eq(&'-') {
+                        Some(_) => match chars.next_if(|c| *c != ']') {
+                            Some(end) => end,
+                            None => {
+                                // A trailing dash is matched literally
+                                ranges.push(('-', '-'));
+                                start
+                            }
+                        },
+                        None => start,
+                    };
+                    if end < start {
+                        return Err(PatternParseError {
+                            msg: format!("Invalid range '{}-{}' in '{}'", start, end, segment),
+                        });
+                    }
+                    ranges.push((start, end));
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+    if tokens.iter().all(|token| matches!(token, Token::Char(_))) {
+        Ok(Segment::Literal(segment.to_string()))
+    } else {
+        Ok(Segment::Wildcard(tokens))
+    }
+}
+
+/// Matches a name against the tokens of a segment, backtracking to the last `*` on a mismatch
+fn tokens_match(tokens: &[Token], name: &[char], case_sensitive: bool) -> bool {
+    let (mut token_index, mut name_index) = (0, 0);
+    // The position after the last `*` and the name index it is currently matched up to
+    let mut backtrack = None;
+    while name_index < name.len() {
+        match tokens.get(token_index) {
+            Some(Token::AnyChars) => {
+                token_index += 1;
+                backtrack = Some((token_index, name_index));
+                continue;
+            }
+            Some(token) if token_matches(token, name[name_index], case_sensitive) => {
+                token_index += 1;
+                name_index += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((after_star, matched_to)) => {
+                token_index = after_star;
+                name_index = matched_to + 1;
+                backtrack = Some((after_star, matched_to + 1));
+            }
+            None => return false,
+        }
+    }
+    tokens[token_index..]
+        .iter()
+        .all(|token| matches!(token, Token::AnyChars))
+}
+
+fn token_matches(token: &Token, c: char, case_sensitive: bool) -> bool {
+    match token {
+        Token::Char(expected) => chars_equal(*expected, c, case_sensitive),
+        Token::AnyChar => true,
+        Token::AnyChars => false,
+        Token::Class { negated, ranges } => {
+            // Without case sensitivity, the character also matches when its uppercase form is
+            // within the uppercase bounds of a range, so [a-z] matches A and [A-Z] matches a
+            let contained = ranges.iter().any(|(start, end)| {
+                (*start..=*end).contains(&c)
+                    || (!case_sensitive && (upcase(*start)..=upcase(*end)).contains(&upcase(c)))
+            });
+            contained != *negated
+        }
+    }
+}
+
+fn names_equal(a: &str, b: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        hive::names_equal(a, b)
+    }
+}
+
+fn chars_equal(a: char, b: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        upcase(a) == upcase(b)
+    }
+}
+
+/// Upcases a character the way the hive compares names, code unit by code unit, so characters
+/// outside the Basic Multilingual Plane are left unchanged
+fn upcase(c: char) -> char {
+    u16::try_from(c as u32)
+        .ok()
+        .and_then(|unit| char::from_u32(hive::upcase(unit) as u32))
+        .unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_hive::{build_hive, TestKey};
+
+    #[test]
+    fn matches_key_paths() {
+        let cases = [
+            // `**` matches any number of segments, including none
+            (r"**", "", true),
+            (r"**", r"A\B\C", true),
+            (r"Software\**\Run", r"Software\Run", true),
+            (r"Software\**\Run", r"Software\Microsoft\Windows\Run", true),
+            (r"Software\**\Run", r"Software\Microsoft\RunOnce", false),
+            (r"**\Parameters", "Parameters", true),
+            (r"Software\**", "Software", true),
+            (r"Software\**", "System", false),
+            // Wildcards stay within a segment
+            (r"ControlSet*\Services", r"ControlSet001\Services", true),
+            (r"*\Services", r"ControlSet001\Old\Services", false),
+            (r"Tcpip?", "Tcpip6", true),
+            (r"Tcpip?", "Tcpip", false),
+            // Character classes and their negation
+            (r"ControlSet00[1-3]", "ControlSet002", true),
+            (r"ControlSet00[1-3]", "ControlSet004", false),
+            (r"ControlSet00[!1-3]", "ControlSet004", true),
+            (r"ControlSet00[!1-3]", "ControlSet001", false),
+            (r"Key[^0-9]", "KeyA", true),
+            (r"Key[!]]", "Key]", false),
+            (r"Key[!]]", "Key!", true),
+            (r"Star[*]", "Star*", true),
+            (r"Star[*]", "Starry", false),
+            // Case folding, including non-ASCII names and class bounds
+            (r"software\MICROSOFT", r"SOFTWARE\microsoft", true),
+            (r"Ünïcode", "ÜNÏCODE", true),
+            (r"[a-c]*", "Branch", true),
+            (r"[A-C]*", "branch", true),
+            // Trailing and repeated backslashes do not add empty segments
+            (r"Software\", "Software", true),
+            (r"Software\", r"Software\Sub", false),
+            (r"Software\\Microsoft\", r"Software\Microsoft\", true),
+        ];
+        for (pattern, path, expected) in cases {
+            let parsed = KeyPathPattern::parse(pattern).unwrap();
+            assert_eq!(
+                parsed.matches(path),
+                expected,
+                "pattern: {}, path: {}",
+                pattern,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn matches_case_sensitively() {
+        let cases = [
+            (r"Software\Microsoft", r"Software\Microsoft", true),
+            (r"Software\Microsoft", r"software\Microsoft", false),
+            (r"Soft*", "SOFTWARE", false),
+            (r"[a-z]*", "Software", false),
+        ];
+        for (pattern, path, expected) in cases {
+            let parsed = KeyPathPattern::parse(pattern)
+                .unwrap()
+                .with_case_sensitive(true);
+            assert_eq!(
+                parsed.matches(path),
+                expected,
+                "pattern: {}, path: {}",
+                pattern,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_classes() {
+        for pattern in [r"Key[a-", r"Key[z-a]", r"Key["] {
+            assert!(KeyPathPattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn walks_matching_keys() {
+        let hive = build_hive(
+            &TestKey::new("ROOT").with_subkey(
+                TestKey::new("ControlSet001").with_subkey(
+                    TestKey::new("Services")
+                        .with_subkey(TestKey::new("Tcpip").with_subkey(TestKey::new("Parameters")))
+                        .with_subkey(TestKey::new("Dhcp")),
+                ),
+            ),
+        );
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let hive = Hive::new(hive::parse_registry(hive, &pool).unwrap());
+        let pattern = KeyPathPattern::parse(r"controlset*\services\**\parameters").unwrap();
+        let paths: Vec<String> = pattern
+            .matching_keys(&hive)
+            .map(|key| key.unwrap().path())
+            .collect();
+        assert_eq!(paths, [r"ControlSet001\Services\Tcpip\Parameters"]);
+    }
+}
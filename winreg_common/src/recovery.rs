@@ -0,0 +1,653 @@
+use crate::hive::{HiveBaseBlock, HIVE_BASE_BLOCK_SIZE};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const LOG_FILE_EXTENSIONS: [&str; 2] = ["LOG1", "LOG2"];
+const LOG_BASE_BLOCK_SIZE: usize = 512;
+const LOG_BASE_BLOCK_SIG: &str = "regf";
+const LOG_ENTRY_SIG: &str = "HvLE";
+const LOG_ENTRY_HEADER_SIZE: usize = 40;
+const LOG_ENTRY_HASHED_HEADER_SIZE: usize = 32;
+const DIRTY_VECTOR_SIG: &str = "DIRT";
+const DIRTY_PAGE_SIZE: usize = 512;
+const SECTOR_SIZE: usize = 512;
+const MARVIN32_SEED: u64 = 0x82EF_4D88_7A4E_55C5;
+
+// Offsets of the base block fields which are read or updated during recovery
+const PRIMARY_SEQUENCE_NUMBER_OFFSET: usize = 4;
+const SECONDARY_SEQUENCE_NUMBER_OFFSET: usize = 8;
+const FILE_TYPE_OFFSET: usize = 28;
+const HIVE_BINS_DATA_SIZE_OFFSET: usize = 40;
+const CHECKSUM_OFFSET: usize = 508;
+const FILE_TYPE_PRIMARY: u32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct RecoveryError {
+    msg: String,
+}
+
+impl RecoveryError {
+    fn new(msg: impl Into<String>) -> Self {
+        RecoveryError { msg: msg.into() }
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+impl fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for RecoveryError {}
+
+/// A primary file with the transaction logs applied to it
+#[derive(Debug, Clone)]
+pub struct ReplayedHive {
+    hive: Bytes,
+    skipped_logs: Vec<(usize, RecoveryError)>,
+}
+
+impl ReplayedHive {
+    /// Returns the recovered hive, which can be passed to [`crate::hive::parse_registry`]
+    pub fn hive(&self) -> &Bytes {
+        &self.hive
+    }
+
+    pub fn into_hive(self) -> Bytes {
+        self.hive
+    }
+
+    /// Returns the logs which could not be parsed and were left out of the recovery, by their
+    /// index in the given logs
+    pub fn skipped_logs(&self) -> &[(usize, RecoveryError)] {
+        &self.skipped_logs
+    }
+}
+
+/// A parsed .LOG1/.LOG2 transaction log file.
+/// Format specification: https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#transaction-log-files
+#[derive(Debug)]
+pub struct TransactionLog {
+    base_block: LogBaseBlock,
+    format: LogFormat,
+}
+
+#[derive(Debug)]
+pub enum LogFormat {
+    /// Log entries, used since Windows 8.1
+    Entries(Vec<LogEntry>),
+    /// A dirty vector followed by dirty pages, used before Windows 8.1
+    DirtyVector(Vec<DirtyPage>),
+}
+
+/// The partial (512 bytes) base block at the start of a transaction log file
+#[derive(Debug)]
+pub struct LogBaseBlock {
+    primary_sequence_number: u32,
+    secondary_sequence_number: u32,
+    file_type: u32,
+    hive_bins_data_size: u32,
+    block: Bytes,
+}
+
+#[derive(Debug)]
+pub struct LogEntry {
+    flags: u32,
+    sequence_number: u32,
+    hive_bins_data_size: u32,
+    dirty_pages: Vec<DirtyPage>,
+}
+
+#[derive(Debug)]
+pub struct DirtyPage {
+    offset: u32,
+    data: Bytes,
+}
+
+impl TransactionLog {
+    pub fn parse(bytes: Bytes) -> Result<Self, RecoveryError> {
+        let base_block = LogBaseBlock::build(&bytes)?;
+        let log_data = bytes.slice(LOG_BASE_BLOCK_SIZE..);
+        let format = if log_data.starts_with(DIRTY_VECTOR_SIG.as_bytes()) {
+            LogFormat::DirtyVector(parse_dirty_vector(
+                log_data,
+                base_block.hive_bins_data_size(),
+            )?)
+        } else {
+            LogFormat::Entries(parse_log_entries(log_data))
+        };
+        Ok(TransactionLog { base_block, format })
+    }
+
+    pub fn base_block(&self) -> &LogBaseBlock {
+        &self.base_block
+    }
+
+    pub fn format(&self) -> &LogFormat {
+        &self.format
+    }
+}
+
+impl LogBaseBlock {
+    fn build(bytes: &Bytes) -> Result<Self, RecoveryError> {
+        if bytes.len() < LOG_BASE_BLOCK_SIZE {
+            return Err(RecoveryError::new(
+                "Transaction log is smaller than its base block",
+            ));
+        }
+        let block = bytes.slice(..LOG_BASE_BLOCK_SIZE);
+        if !block.starts_with(LOG_BASE_BLOCK_SIG.as_bytes()) {
+            return Err(RecoveryError::new(
+                "Transaction log base block has an invalid signature",
+            ));
+        }
+        if read_u32(&block, CHECKSUM_OFFSET) != HiveBaseBlock::calculate_checksum(&block) {
+            return Err(RecoveryError::new(
+                "Transaction log base block has an invalid checksum",
+            ));
+        }
+        Ok(LogBaseBlock {
+            primary_sequence_number: read_u32(&block, PRIMARY_SEQUENCE_NUMBER_OFFSET),
+            secondary_sequence_number: read_u32(&block, SECONDARY_SEQUENCE_NUMBER_OFFSET),
+            file_type: read_u32(&block, FILE_TYPE_OFFSET),
+            hive_bins_data_size: read_u32(&block, HIVE_BINS_DATA_SIZE_OFFSET),
+            block,
+        })
+    }
+
+    pub fn primary_sequence_number(&self) -> u32 {
+        self.primary_sequence_number
+    }
+
+    pub fn secondary_sequence_number(&self) -> u32 {
+        self.secondary_sequence_number
+    }
+
+    pub fn file_type(&self) -> u32 {
+        self.file_type
+    }
+
+    pub fn hive_bins_data_size(&self) -> u32 {
+        self.hive_bins_data_size
+    }
+}
+
+impl LogEntry {
+    /// Parses a single log entry, returning `None` if it is truncated or fails hash validation
+    fn build(entry: &Bytes) -> Option<Self> {
+        let mut header = &entry[..LOG_ENTRY_HEADER_SIZE];
+        header.advance(LOG_ENTRY_SIG.len() + 4);
+        let flags = header.get_u32_le();
+        let sequence_number = header.get_u32_le();
+        let hive_bins_data_size = header.get_u32_le();
+        let dirty_pages_count = header.get_u32_le() as usize;
+        let hash_1 = header.get_u64_le();
+        let hash_2 = header.get_u64_le();
+
+        if marvin32(&entry[..LOG_ENTRY_HASHED_HEADER_SIZE], MARVIN32_SEED) != hash_2
+            || marvin32(&entry[LOG_ENTRY_HEADER_SIZE..], MARVIN32_SEED) != hash_1
+        {
+            return None;
+        }
+
+        let references_size = dirty_pages_count.checked_mul(8)?;
+        let mut references = entry.get(LOG_ENTRY_HEADER_SIZE..)?.get(..references_size)?;
+        let mut page_offset = LOG_ENTRY_HEADER_SIZE + references_size;
+        let mut dirty_pages = Vec::with_capacity(dirty_pages_count);
+        for _ in 0..dirty_pages_count {
+            let offset = references.get_u32_le();
+            let size = references.get_u32_le() as usize;
+            let end = page_offset.checked_add(size)?;
+            if end > entry.len() {
+                return None;
+            }
+            dirty_pages.push(DirtyPage {
+                offset,
+                data: entry.slice(page_offset..end),
+            });
+            page_offset = end;
+        }
+
+        Some(LogEntry {
+            flags,
+            sequence_number,
+            hive_bins_data_size,
+            dirty_pages,
+        })
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn hive_bins_data_size(&self) -> u32 {
+        self.hive_bins_data_size
+    }
+
+    pub fn dirty_pages(&self) -> &[DirtyPage] {
+        &self.dirty_pages
+    }
+}
+
+impl DirtyPage {
+    /// Offset of the page, relative to the start of the hive bins data
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+/// Reads consecutive log entries until the end of the log, an invalid entry or a gap in the
+/// sequence numbers. Anything after that point was never completely written and is ignored.
+fn parse_log_entries(mut log_data: Bytes) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = vec![];
+    while log_data.len() >= LOG_ENTRY_HEADER_SIZE && log_data.starts_with(LOG_ENTRY_SIG.as_bytes())
+    {
+        let size = read_u32(&log_data, LOG_ENTRY_SIG.len()) as usize;
+        if size < LOG_ENTRY_HEADER_SIZE
+            || !size.is_multiple_of(SECTOR_SIZE)
+            || size > log_data.len()
+        {
+            break;
+        }
+        let Some(entry) = LogEntry::build(&log_data.slice(..size)) else {
+            break;
+        };
+        if let Some(previous) = entries.last() {
+            if previous.sequence_number.wrapping_add(1) != entry.sequence_number {
+                break;
+            }
+        }
+        entries.push(entry);
+        log_data.advance(size);
+    }
+    entries
+}
+
+/// Reads the dirty pages marked in a legacy dirty vector. Each bit of the bitmap marks a
+/// 512 byte page of the hive bins data, and the pages follow the bitmap in order.
+fn parse_dirty_vector(
+    log_data: Bytes,
+    hive_bins_data_size: u32,
+) -> Result<Vec<DirtyPage>, RecoveryError> {
+    let bitmap_size = hive_bins_data_size as usize / DIRTY_PAGE_SIZE / 8;
+    let bitmap_end = DIRTY_VECTOR_SIG.len() + bitmap_size;
+    let bitmap = log_data
+        .get(DIRTY_VECTOR_SIG.len()..bitmap_end)
+        .ok_or_else(|| RecoveryError::new("Transaction log dirty vector is truncated"))?;
+
+    // Dirty pages start at the next sector boundary after the dirty vector
+    let mut page_offset =
+        (LOG_BASE_BLOCK_SIZE + bitmap_end).next_multiple_of(SECTOR_SIZE) - LOG_BASE_BLOCK_SIZE;
+    let mut dirty_pages = vec![];
+    for (index, byte) in bitmap.iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (1 << bit) == 0 {
+                continue;
+            }
+            let end = page_offset + DIRTY_PAGE_SIZE;
+            if end > log_data.len() {
+                return Err(RecoveryError::new(
+                    "Transaction log dirty page is truncated",
+                ));
+            }
+            dirty_pages.push(DirtyPage {
+                offset: ((index * 8 + bit) * DIRTY_PAGE_SIZE) as u32,
+                data: log_data.slice(page_offset..end),
+            });
+            page_offset = end;
+        }
+    }
+    Ok(dirty_pages)
+}
+
+/// Returns the transaction logs stored next to a hive file, e.g. `SYSTEM.LOG1` and `SYSTEM.LOG2`
+/// for `SYSTEM` or `ntuser.dat.LOG1` for `ntuser.dat`. Lower case extensions are found as well.
+pub fn find_transaction_logs(hive_path: &Path) -> Vec<PathBuf> {
+    let Some(file_name) = hive_path.file_name() else {
+        return vec![];
+    };
+    LOG_FILE_EXTENSIONS
+        .iter()
+        .filter_map(|extension| {
+            [extension.to_string(), extension.to_lowercase()]
+                .into_iter()
+                .map(|extension| {
+                    let mut log_name = file_name.to_os_string();
+                    log_name.push(".");
+                    log_name.push(extension);
+                    hive_path.with_file_name(log_name)
+                })
+                .find(|log_path| log_path.is_file())
+        })
+        .collect()
+}
+
+/// Applies the dirty pages in the given transaction logs to an in-memory copy of a dirty primary
+/// file, returning the recovered hive. Clean primary files are returned unchanged. Logs which can
+/// not be parsed are skipped and reported in the result, as long as another log can be applied.
+pub fn replay(primary: Bytes, logs: &[Bytes]) -> Result<ReplayedHive, RecoveryError> {
+    let base_block = HiveBaseBlock::build(&mut primary.clone())
+        .map_err(|e| RecoveryError::new(format!("Failed parsing primary base block: {}", e)))?;
+    if !base_block.is_dirty() {
+        return Ok(ReplayedHive {
+            hive: primary,
+            skipped_logs: vec![],
+        });
+    }
+
+    let mut skipped_logs = vec![];
+    let logs: Vec<TransactionLog> = logs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, log)| match TransactionLog::parse(log.clone()) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                skipped_logs.push((index, e));
+                None
+            }
+        })
+        .collect();
+    if logs.is_empty() {
+        return Err(RecoveryError::new(
+            "The hive is dirty, but no valid transaction log was given",
+        ));
+    }
+
+    let mut hive = BytesMut::from(&primary[..]);
+    let has_log_entries = logs
+        .iter()
+        .any(|log| matches!(log.format(), LogFormat::Entries(_)));
+    if has_log_entries {
+        apply_log_entries(&mut hive, &base_block, &logs)?;
+    } else {
+        apply_dirty_vector(&mut hive, &logs)?;
+    }
+    Ok(ReplayedHive {
+        hive: hive.freeze(),
+        skipped_logs,
+    })
+}
+
+fn apply_log_entries(
+    hive: &mut BytesMut,
+    base_block: &HiveBaseBlock,
+    logs: &[TransactionLog],
+) -> Result<(), RecoveryError> {
+    // Entries older than the last completed write to the primary file are already applied
+    let mut entries: Vec<&LogEntry> = logs
+        .iter()
+        .filter_map(|log| match log.format() {
+            LogFormat::Entries(entries) => Some(entries),
+            LogFormat::DirtyVector(_) => None,
+        })
+        .flatten()
+        .filter(|entry| entry.sequence_number() >= base_block.secondary_sequence_number())
+        .collect();
+    entries.sort_by_key(|entry| entry.sequence_number());
+    entries.dedup_by_key(|entry| entry.sequence_number());
+
+    // The primary file was last completely written up to the secondary sequence number, so
+    // the entries must continue from there or the pages in between are missing
+    if let Some(first) = entries.first() {
+        if first.sequence_number() != base_block.secondary_sequence_number() {
+            return Err(RecoveryError::new(format!(
+                "The transaction logs start at sequence number {}, but the primary file expects {}",
+                first.sequence_number(),
+                base_block.secondary_sequence_number()
+            )));
+        }
+    }
+
+    let mut last_sequence_number = None;
+    for entry in entries {
+        if let Some(last) = last_sequence_number {
+            if entry.sequence_number() != u32::wrapping_add(last, 1) {
+                break;
+            }
+        }
+        apply_dirty_pages(hive, entry.hive_bins_data_size(), entry.dirty_pages())?;
+        last_sequence_number = Some(entry.sequence_number());
+    }
+
+    let Some(last_sequence_number) = last_sequence_number else {
+        return Err(RecoveryError::new(
+            "The transaction logs contain no entries newer than the primary file",
+        ));
+    };
+    let sequence_number = last_sequence_number.wrapping_add(1);
+    write_u32(hive, PRIMARY_SEQUENCE_NUMBER_OFFSET, sequence_number);
+    write_u32(hive, SECONDARY_SEQUENCE_NUMBER_OFFSET, sequence_number);
+    finish_base_block(hive);
+    Ok(())
+}
+
+fn apply_dirty_vector(hive: &mut BytesMut, logs: &[TransactionLog]) -> Result<(), RecoveryError> {
+    // Only a log whose own base block was completely written can be applied, and the most
+    // recent one wins
+    let (base_block, dirty_pages) = logs
+        .iter()
+        .filter_map(|log| match log.format() {
+            LogFormat::DirtyVector(dirty_pages) => Some((log.base_block(), dirty_pages)),
+            LogFormat::Entries(_) => None,
+        })
+        .filter(|(base_block, _)| {
+            base_block.primary_sequence_number() == base_block.secondary_sequence_number()
+        })
+        .max_by_key(|(base_block, _)| base_block.primary_sequence_number())
+        .ok_or_else(|| RecoveryError::new("No transaction log has a consistent base block"))?;
+
+    apply_dirty_pages(hive, base_block.hive_bins_data_size(), dirty_pages)?;
+
+    // The log holds the base block that was about to be written to the primary file
+    hive[..LOG_BASE_BLOCK_SIZE].copy_from_slice(&base_block.block);
+    write_u32(hive, FILE_TYPE_OFFSET, FILE_TYPE_PRIMARY);
+    finish_base_block(hive);
+    Ok(())
+}
+
+fn apply_dirty_pages(
+    hive: &mut BytesMut,
+    hive_bins_data_size: u32,
+    dirty_pages: &[DirtyPage],
+) -> Result<(), RecoveryError> {
+    let hive_bins_data_size = hive_bins_data_size as usize;
+    let current_size = hive.len().saturating_sub(HIVE_BASE_BLOCK_SIZE);
+
+    // A hive only grows by appending hive bins, which are then part of the dirty pages. Checking
+    // this bounds the size of the recovered hive by the size of its inputs.
+    let dirty_size: usize = dirty_pages.iter().map(|page| page.data().len()).sum();
+    if hive_bins_data_size > current_size + dirty_size {
+        return Err(RecoveryError::new(
+            "Transaction log grows the hive beyond its dirty pages",
+        ));
+    }
+    hive.resize(HIVE_BASE_BLOCK_SIZE + hive_bins_data_size, 0);
+    write_u32(hive, HIVE_BINS_DATA_SIZE_OFFSET, hive_bins_data_size as u32);
+
+    for page in dirty_pages {
+        let start = HIVE_BASE_BLOCK_SIZE + page.offset() as usize;
+        let end = start + page.data().len();
+        if end > hive.len() {
+            return Err(RecoveryError::new(
+                "Transaction log dirty page is outside of the hive bins data",
+            ));
+        }
+        hive[start..end].copy_from_slice(page.data());
+    }
+    Ok(())
+}
+
+fn finish_base_block(hive: &mut BytesMut) {
+    let checksum = HiveBaseBlock::calculate_checksum(&hive[..LOG_BASE_BLOCK_SIZE]);
+    write_u32(hive, CHECKSUM_OFFSET, checksum);
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    (&data[offset..offset + 4]).get_u32_le()
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    (&mut data[offset..offset + 4]).put_u32_le(value);
+}
+
+/// Marvin32 hash, as used to validate log entries
+fn marvin32(data: &[u8], seed: u64) -> u64 {
+    let mut lo = seed as u32;
+    let mut hi = (seed >> 32) as u32;
+
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        lo = lo.wrapping_add(u32::from_le_bytes([block[0], block[1], block[2], block[3]]));
+        marvin32_mix(&mut lo, &mut hi);
+    }
+
+    // The remaining bytes are padded with a single 0x80 byte
+    let last = blocks
+        .remainder()
+        .iter()
+        .rev()
+        .fold(0x80, |last, &byte| (last << 8) | byte as u32);
+    lo = lo.wrapping_add(last);
+    marvin32_mix(&mut lo, &mut hi);
+    marvin32_mix(&mut lo, &mut hi);
+
+    ((hi as u64) << 32) | lo as u64
+}
+
+fn marvin32_mix(lo: &mut u32, hi: &mut u32) {
+    *hi ^= *lo;
+    *lo = lo.rotate_left(20);
+    *lo = lo.wrapping_add(*hi);
+    *hi = hi.rotate_left(9);
+    *hi ^= *lo;
+    *lo = lo.rotate_left(27);
+    *lo = lo.wrapping_add(*hi);
+    *hi = hi.rotate_left(19);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{parse_registry, Hive, RegValue, REG_DWORD};
+    use crate::test_hive::{build_hive, set_checksum, TestKey};
+    use rayon::ThreadPoolBuilder;
+
+    const TEST_SEED: u64 = 0x004F_B61A_001B_DBCC;
+
+    const FILE_TYPE_LOG: u32 = 6;
+    const PAGE_SIZE: usize = 4096;
+
+    fn hive_with_dword(data: u32) -> Vec<u8> {
+        build_hive(&TestKey::new("ROOT").with_value("Counter", REG_DWORD, &data.to_le_bytes()))
+            .to_vec()
+    }
+
+    fn set_sequence_numbers(block: &mut [u8], primary: u32, secondary: u32) {
+        write_u32(block, PRIMARY_SEQUENCE_NUMBER_OFFSET, primary);
+        write_u32(block, SECONDARY_SEQUENCE_NUMBER_OFFSET, secondary);
+        set_checksum(block);
+    }
+
+    /// Builds a log entry holding a single dirty page, padded to a whole number of sectors
+    fn log_entry(sequence_number: u32, hive_bins_data_size: u32, page: &[u8]) -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend((page.len() as u32).to_le_bytes());
+        body.extend(page);
+        let size = (LOG_ENTRY_HEADER_SIZE + body.len()).next_multiple_of(SECTOR_SIZE);
+        body.resize(size - LOG_ENTRY_HEADER_SIZE, 0);
+
+        let mut entry = LOG_ENTRY_SIG.as_bytes().to_vec();
+        for field in [size as u32, 0, sequence_number, hive_bins_data_size, 1] {
+            entry.extend(field.to_le_bytes());
+        }
+        entry.extend(marvin32(&body, MARVIN32_SEED).to_le_bytes());
+        let header_hash = marvin32(&entry, MARVIN32_SEED);
+        entry.extend(header_hash.to_le_bytes());
+        entry.extend(body);
+        entry
+    }
+
+    #[test]
+    fn replays_log_entries_onto_a_dirty_hive() {
+        let clean = hive_with_dword(1);
+        let written = hive_with_dword(2);
+        let hive_bins_data_size = (clean.len() - HIVE_BASE_BLOCK_SIZE) as u32;
+
+        // The primary file was marked dirty at sequence number 5, but the page changed by that
+        // write only reached the log
+        let mut primary = clean.clone();
+        set_sequence_numbers(&mut primary, 5, 4);
+        let mut log = clean[..LOG_BASE_BLOCK_SIZE].to_vec();
+        write_u32(&mut log, FILE_TYPE_OFFSET, FILE_TYPE_LOG);
+        set_sequence_numbers(&mut log, 5, 5);
+        log.extend(log_entry(
+            4,
+            hive_bins_data_size,
+            &written[HIVE_BASE_BLOCK_SIZE..HIVE_BASE_BLOCK_SIZE + PAGE_SIZE],
+        ));
+
+        let replayed = replay(Bytes::from(primary), &[Bytes::from(log)]).unwrap();
+        assert!(replayed.skipped_logs().is_empty());
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let hive = Hive::new(parse_registry(replayed.into_hive(), &pool).unwrap());
+        let base_block = hive.primary_file().base_block();
+        assert!(!base_block.is_dirty());
+        assert!(base_block.checksum_error().is_none());
+        assert_eq!(base_block.secondary_sequence_number(), 5);
+        let values = hive.root().unwrap().values().unwrap();
+        assert_eq!(values[0].reg_value().unwrap(), RegValue::Dword(2));
+    }
+
+    #[test]
+    fn rejects_a_dirty_hive_without_valid_logs() {
+        let mut primary = hive_with_dword(1);
+        set_sequence_numbers(&mut primary, 5, 4);
+        let error = replay(Bytes::from(primary), &[Bytes::from_static(b"regf")]).unwrap_err();
+        assert_eq!(
+            error.msg(),
+            "The hive is dirty, but no valid transaction log was given"
+        );
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn marvin32_matches_published_vectors() {
+        let vectors = [
+            ("", 0x30ED_35C1_00CD_3C7D),
+            ("af", 0x48E7_3FC7_7D75_DDC1),
+            ("e70f", 0xB5F6_E1FC_485D_BFF8),
+            ("37f495", 0xF0B0_7C78_9B8C_F7E8),
+            ("8642dc59", 0x7008_F2E8_7E9C_F556),
+            ("153fb79826", 0xE6C0_8C6D_A2AF_A997),
+            ("0932e6246c47", 0x6F04_BF1A_5EA2_4060),
+            ("ab427ea8d10fc7", 0xE118_47E4_F067_8C41),
+        ];
+        for (data, hash) in vectors {
+            assert_eq!(
+                marvin32(&hex_bytes(data), TEST_SEED),
+                hash,
+                "data: {}",
+                data
+            );
+        }
+    }
+}
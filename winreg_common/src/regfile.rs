@@ -0,0 +1,581 @@
+use crate::hive::{REG_BINARY, REG_DWORD, REG_EXPAND_SZ, REG_LINK, REG_MULTI_SZ, REG_SZ};
+use crate::tree::{KeyNode, ValueNode};
+use bytes::Bytes;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const REGEDIT5_HEADER: &str = "Windows Registry Editor Version 5.00";
+const REGEDIT4_HEADER: &str = "REGEDIT4";
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const DEFAULT_VALUE_NAME: char = '@';
+const DELETE_MARKER: char = '-';
+const COMMENT_MARKER: char = ';';
+const LINE_CONTINUATION: char = '\\';
+const DWORD_PREFIX: &str = "dword:";
+const HEX_PREFIX: &str = "hex";
+const MAX_DWORD_DIGITS: usize = 8;
+
+/// The format version given in the header of a .reg file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegFileVersion {
+    /// `REGEDIT4`, written in the ANSI code page
+    Regedit4,
+    /// `Windows Registry Editor Version 5.00`, written in UTF-16LE with a byte order mark
+    Regedit5,
+}
+
+/// The value deleted by a `"name"=-` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedValue {
+    key_path: String,
+    name: String,
+}
+
+/// A parsed .reg file. The keys and values it creates are collected into a tree below an
+/// unnamed root key, whose subkeys are root keys such as `HKEY_LOCAL_MACHINE`. Deletions are
+/// applied to the tree in file order and also listed separately.
+#[derive(Debug, Clone)]
+pub struct RegFile {
+    version: RegFileVersion,
+    root: KeyNode,
+    deleted_keys: Vec<String>,
+    deleted_values: Vec<DeletedValue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegFileParseError {
+    // The 1-based line the error was found on, or 0 for errors concerning the whole file
+    line: usize,
+    msg: String,
+}
+
+impl RegFileParseError {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+impl fmt::Display for RegFileParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.msg),
+            line => write!(f, "line {}: {}", line, self.msg),
+        }
+    }
+}
+
+impl std::error::Error for RegFileParseError {}
+
+impl DeletedValue {
+    pub fn key_path(&self) -> &str {
+        &self.key_path
+    }
+
+    /// Returns the name of the deleted value, empty for the default value
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl RegFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegFileParseError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|err| RegFileParseError {
+            line: 0,
+            msg: format!("Failed reading {}: {}", path.display(), err),
+        })?;
+        RegFile::parse(&data)
+    }
+
+    /// Parses a .reg file, decoding it as UTF-16LE when it starts with a byte order mark and
+    /// otherwise as UTF-8, falling back to Latin-1 for ANSI files
+    pub fn parse(data: &[u8]) -> Result<Self, RegFileParseError> {
+        RegFile::parse_str(&decode_text(data))
+    }
+
+    pub fn parse_str(text: &str) -> Result<Self, RegFileParseError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line));
+        let version = loop {
+            let Some((line_number, line)) = lines.next() else {
+                return Err(RegFileParseError {
+                    line: 0,
+                    msg: "The file is empty".to_string(),
+                });
+            };
+            match line.trim() {
+                "" => continue,
+                REGEDIT5_HEADER => break RegFileVersion::Regedit5,
+                REGEDIT4_HEADER => break RegFileVersion::Regedit4,
+                header => {
+                    return Err(RegFileParseError {
+                        line: line_number,
+                        msg: format!("Unknown header '{}'", header),
+                    })
+                }
+            }
+        };
+
+        let mut parser = Parser {
+            reg_file: RegFile {
+                version,
+                root: KeyNode::new(""),
+                deleted_keys: vec![],
+                deleted_values: vec![],
+            },
+            current_key: None,
+        };
+        while let Some((line_number, line)) = lines.next() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with(COMMENT_MARKER) {
+                continue;
+            }
+            let result = if line.starts_with('[') {
+                parser.parse_key_line(line)
+            } else {
+                parser.parse_value_line(line, &mut lines)
+            };
+            result.map_err(|msg| RegFileParseError {
+                line: line_number,
+                msg,
+            })?;
+        }
+        Ok(parser.reg_file)
+    }
+
+    pub fn version(&self) -> RegFileVersion {
+        self.version
+    }
+
+    /// Returns the unnamed key holding the root keys created by the file
+    pub fn root(&self) -> &KeyNode {
+        &self.root
+    }
+
+    /// Returns the key at the given full path, e.g. `HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft`
+    pub fn open_key(&self, path: &str) -> Option<&KeyNode> {
+        self.root.open_key(path)
+    }
+
+    /// Returns the paths of the keys deleted by `[-path]` lines
+    pub fn deleted_keys(&self) -> &[String] {
+        &self.deleted_keys
+    }
+
+    pub fn deleted_values(&self) -> &[DeletedValue] {
+        &self.deleted_values
+    }
+}
+
+struct Parser {
+    reg_file: RegFile,
+    // The key values are added to, none after a key deletion
+    current_key: Option<String>,
+}
+
+impl Parser {
+    fn parse_key_line(&mut self, line: &str) -> Result<(), String> {
+        let end = line
+            .rfind(']')
+            .ok_or_else(|| "Missing ']' after key path".to_string())?;
+        let path = &line[1..end];
+        match path.strip_prefix(DELETE_MARKER) {
+            Some(path) => {
+                let path = non_empty_path(path)?;
+                self.reg_file.root.remove_key(path);
+                self.reg_file.deleted_keys.push(path.to_string());
+                self.current_key = None;
+            }
+            None => {
+                let path = non_empty_path(path)?;
+                self.reg_file.root.create_key(path);
+                self.current_key = Some(path.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_value_line<'t>(
+        &mut self,
+        line: &str,
+        lines: &mut impl Iterator<Item = (usize, &'t str)>,
+    ) -> Result<(), String> {
+        let Some(key_path) = self.current_key.clone() else {
+            return Err("Value outside of a key".to_string());
+        };
+        let (name, rest) = match line.strip_prefix(DEFAULT_VALUE_NAME) {
+            Some(rest) => (String::new(), rest),
+            None => match line.strip_prefix('"') {
+                Some(quoted) => parse_quoted(quoted)?,
+                None => return Err(format!("Expected a value name in '{}'", line)),
+            },
+        };
+        let data = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| format!("Expected '=' after value name '{}'", name))?
+            .trim_start();
+
+        if let Some(rest) = data.strip_prefix(DELETE_MARKER) {
+            expect_line_end(rest)?;
+            let key = self.reg_file.root.create_key(&key_path);
+            key.remove_value(&name);
+            self.reg_file
+                .deleted_values
+                .push(DeletedValue { key_path, name });
+            return Ok(());
+        }
+
+        let value = if let Some(quoted) = data.strip_prefix('"') {
+            let (string, rest) = parse_quoted(quoted)?;
+            expect_line_end(rest)?;
+            ValueNode::new(name, REG_SZ, encode_utf16le_string(&string))
+        } else if let Some(digits) = strip_prefix_ignore_case(data, DWORD_PREFIX) {
+            let (digits, rest) = split_hex_digits(digits);
+            expect_line_end(rest)?;
+            if digits.is_empty() || digits.len() > MAX_DWORD_DIGITS {
+                return Err(format!("Invalid dword data '{}'", data));
+            }
+            let dword = u32::from_str_radix(digits, 16).map_err(|err| err.to_string())?;
+            ValueNode::new(
+                name,
+                REG_DWORD,
+                Bytes::copy_from_slice(&dword.to_le_bytes()),
+            )
+        } else if let Some(hex) = strip_prefix_ignore_case(data, HEX_PREFIX) {
+            let (data_type, hex) = parse_hex_type(hex)?;
+            let mut bytes = parse_hex_bytes(hex, lines)?;
+            // REGEDIT4 files store strings in the ANSI code page, while hives hold UTF-16LE
+            if self.reg_file.version == RegFileVersion::Regedit4
+                && matches!(data_type, REG_SZ | REG_EXPAND_SZ | REG_LINK | REG_MULTI_SZ)
+            {
+                bytes = bytes
+                    .iter()
+                    .flat_map(|byte| u16::from(*byte).to_le_bytes())
+                    .collect();
+            }
+            ValueNode::new(name, data_type, Bytes::from(bytes))
+        } else {
+            return Err(format!("Unsupported value data '{}'", data));
+        };
+        self.reg_file.root.create_key(&key_path).set_value(value);
+        Ok(())
+    }
+}
+
+fn decode_text(data: &[u8]) -> String {
+    if let Some(data) = data.strip_prefix(&UTF16LE_BOM) {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    let data = data.strip_prefix(&UTF8_BOM).unwrap_or(data);
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => data.iter().map(|byte| char::from(*byte)).collect(),
+    }
+}
+
+fn non_empty_path(path: &str) -> Result<&str, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err("Empty key path".to_string());
+    }
+    Ok(path)
+}
+
+/// Parses a quoted string up to its closing quote, returning the unescaped string and the
+/// rest of the line. Only `\\` and `\"` are escapes, as in the files regedit writes, other
+/// backslashes are kept as they are.
+fn parse_quoted(quoted: &str) -> Result<(String, &str), String> {
+    let mut string = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, &quoted[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, '\\')) => string.push('\\'),
+                Some((_, '"')) => string.push('"'),
+                Some((_, other)) => {
+                    string.push('\\');
+                    string.push(other);
+                }
+                None => string.push('\\'),
+            },
+            c => string.push(c),
+        }
+    }
+    Err(format!("Missing closing quote in '\"{}'", quoted))
+}
+
+/// Checks that nothing but whitespace or a comment follows the data
+fn expect_line_end(rest: &str) -> Result<(), String> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with(COMMENT_MARKER) {
+        Ok(())
+    } else {
+        Err(format!("Unexpected '{}' after value data", rest))
+    }
+}
+
+fn strip_prefix_ignore_case<'s>(string: &'s str, prefix: &str) -> Option<&'s str> {
+    let head = string.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &string[prefix.len()..])
+}
+
+fn split_hex_digits(string: &str) -> (&str, &str) {
+    let end = string
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(string.len());
+    string.split_at(end)
+}
+
+/// Parses the `:` of `hex:` or the `(type):` of `hex(type):`, returning the data type and the
+/// hex data after the colon
+fn parse_hex_type(hex: &str) -> Result<(u32, &str), String> {
+    if let Some(data) = hex.strip_prefix(':') {
+        return Ok((REG_BINARY, data));
+    }
+    let invalid = || format!("Invalid hex type in 'hex{}'", hex);
+    let type_digits = hex.strip_prefix('(').ok_or_else(invalid)?;
+    let (digits, rest) = split_hex_digits(type_digits);
+    let data = rest.strip_prefix("):").ok_or_else(invalid)?;
+    if digits.is_empty() || digits.len() > MAX_DWORD_DIGITS {
+        return Err(invalid());
+    }
+    let data_type = u32::from_str_radix(digits, 16).map_err(|_| invalid())?;
+    Ok((data_type, data))
+}
+
+/// Parses comma separated hex bytes, following lines ending in a backslash onto the next line
+fn parse_hex_bytes<'t>(
+    hex: &str,
+    lines: &mut impl Iterator<Item = (usize, &'t str)>,
+) -> Result<Vec<u8>, String> {
+    let mut hex = strip_comment(hex).trim().to_string();
+    while let Some(continued) = hex.strip_suffix(LINE_CONTINUATION) {
+        hex = continued.to_string();
+        let next_line = loop {
+            match lines.next() {
+                Some((_, line)) if line.trim_start().starts_with(COMMENT_MARKER) => continue,
+                Some((_, line)) => break line,
+                None => return Err("Missing line after line continuation".to_string()),
+            }
+        };
+        hex.push_str(strip_comment(next_line).trim());
+    }
+
+    let mut bytes = vec![];
+    let mut tokens = hex.split(',').map(str::trim).peekable();
+    while let Some(token) = tokens.next() {
+        // A trailing comma leaves an empty last token
+        if token.is_empty() && tokens.peek().is_none() {
+            break;
+        }
+        if token.is_empty() || token.len() > 2 || !token.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid hex byte '{}'", token));
+        }
+        bytes.push(u8::from_str_radix(token, 16).map_err(|err| err.to_string())?);
+    }
+    Ok(bytes)
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split(COMMENT_MARKER).next().unwrap_or_default()
+}
+
+/// Encodes a string as REG_SZ data, in UTF-16LE with a terminating null character
+fn encode_utf16le_string(string: &str) -> Bytes {
+    string
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{RegValue, REG_DWORD};
+
+    const HEADER: &str = "Windows Registry Editor Version 5.00\r\n\r\n";
+
+    fn parse(body: &str) -> RegFile {
+        RegFile::parse_str(&format!("{}{}", HEADER, body)).unwrap()
+    }
+
+    fn value(reg_file: &RegFile, key_path: &str, name: &str) -> RegValue {
+        reg_file
+            .open_key(key_path)
+            .unwrap()
+            .value(name)
+            .unwrap()
+            .reg_value()
+    }
+
+    #[test]
+    fn unescapes_only_backslashes_and_quotes() {
+        let reg_file = parse(
+            "[HKEY_CURRENT_USER\\Test]\r\n\
+             \"Path\"=\"C:\\\\Program Files\\\\App\"\r\n\
+             \"Quoted\"=\"say \\\"hi\\\"\"\r\n\
+             \"Kept\"=\"a\\nb\"\r\n\
+             \"Name \\\"with\\\" quotes\"=\"x\" ; comment\r\n\
+             \"Semicolon\"=\"a;b\"\r\n",
+        );
+        let key = "HKEY_CURRENT_USER\\Test";
+        let sz = |string: &str| RegValue::Sz(string.to_string());
+        assert_eq!(value(&reg_file, key, "Path"), sz("C:\\Program Files\\App"));
+        assert_eq!(value(&reg_file, key, "Quoted"), sz("say \"hi\""));
+        assert_eq!(value(&reg_file, key, "Kept"), sz("a\\nb"));
+        assert_eq!(value(&reg_file, key, "Name \"with\" quotes"), sz("x"));
+        assert_eq!(value(&reg_file, key, "Semicolon"), sz("a;b"));
+    }
+
+    #[test]
+    fn parses_typed_hex_data() {
+        let reg_file = parse(
+            "[HKEY_CURRENT_USER\\Test]\r\n\
+             \"Multi\"=hex(7):61,00,62,00,00,00,63,00,00,00,00,00\r\n\
+             \"Expand\"=hex(2):25,00,50,00,25,00,00,00\r\n\
+             \"Qword\"=hex(b):01,00,00,00,00,00,00,00\r\n\
+             \"Binary\"=hex:de,AD,be,ef,\r\n\
+             @=dword:0000002A\r\n",
+        );
+        let key = "HKEY_CURRENT_USER\\Test";
+        assert_eq!(
+            value(&reg_file, key, "Multi"),
+            RegValue::MultiSz(vec!["ab".to_string(), "c".to_string()])
+        );
+        assert_eq!(
+            value(&reg_file, key, "Expand"),
+            RegValue::ExpandSz("%P%".to_string())
+        );
+        assert_eq!(value(&reg_file, key, "Qword"), RegValue::Qword(1));
+        assert_eq!(
+            value(&reg_file, key, "Binary"),
+            RegValue::Binary(Bytes::from_static(&[0xDE, 0xAD, 0xBE, 0xEF]))
+        );
+        assert_eq!(value(&reg_file, key, ""), RegValue::Dword(42));
+        let default = reg_file.open_key(key).unwrap().value("").unwrap();
+        assert!(default.is_default());
+        assert_eq!(default.data_type(), REG_DWORD);
+    }
+
+    #[test]
+    fn follows_line_continuations() {
+        let reg_file = parse(
+            "[HKEY_CURRENT_USER\\Test]\r\n\
+             \"Multi\"=hex(7):61,00,62,00,00,00,\\\r\n\
+             \x20 63,00,00,00,\\\r\n\
+             ; a comment between continued lines\r\n\
+             \x20 00,00\r\n\
+             \"After\"=dword:00000001\r\n",
+        );
+        let key = "HKEY_CURRENT_USER\\Test";
+        assert_eq!(
+            value(&reg_file, key, "Multi"),
+            RegValue::MultiSz(vec!["ab".to_string(), "c".to_string()])
+        );
+        assert_eq!(value(&reg_file, key, "After"), RegValue::Dword(1));
+
+        let error = RegFile::parse_str(&format!(
+            "{}[HKEY_CURRENT_USER\\Test]\r\n\"Data\"=hex:01,\\\r\n",
+            HEADER
+        ))
+        .unwrap_err();
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.msg(), "Missing line after line continuation");
+    }
+
+    #[test]
+    fn applies_deletions_in_file_order() {
+        let reg_file = parse(
+            "[HKEY_CURRENT_USER\\Test\\Gone]\r\n\
+             \"Value\"=dword:00000001\r\n\
+             \r\n\
+             [HKEY_CURRENT_USER\\Test]\r\n\
+             \"Kept\"=\"yes\"\r\n\
+             \"Removed\"=\"no\"\r\n\
+             \"Removed\"=-\r\n\
+             \r\n\
+             [-HKEY_CURRENT_USER\\Test\\Gone]\r\n\
+             \r\n\
+             [HKEY_CURRENT_USER\\Test\\Recreated]\r\n\
+             [-HKEY_CURRENT_USER\\Elsewhere]\r\n",
+        );
+        let key = reg_file.open_key("HKEY_CURRENT_USER\\Test").unwrap();
+        let names: Vec<&str> = key.values().iter().map(ValueNode::name).collect();
+        assert_eq!(names, ["Kept"]);
+        let subkeys: Vec<&str> = key.subkeys().iter().map(KeyNode::name).collect();
+        assert_eq!(subkeys, ["Recreated"]);
+        assert_eq!(
+            reg_file.deleted_keys(),
+            [
+                "HKEY_CURRENT_USER\\Test\\Gone",
+                "HKEY_CURRENT_USER\\Elsewhere"
+            ]
+        );
+        assert_eq!(
+            reg_file.deleted_values(),
+            [DeletedValue {
+                key_path: "HKEY_CURRENT_USER\\Test".to_string(),
+                name: "Removed".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_values_after_a_key_deletion() {
+        let error = RegFile::parse_str(&format!(
+            "{}[-HKEY_CURRENT_USER\\Test]\r\n\"Value\"=dword:00000001\r\n",
+            HEADER
+        ))
+        .unwrap_err();
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.msg(), "Value outside of a key");
+    }
+
+    #[test]
+    fn widens_regedit4_strings() {
+        let reg_file = RegFile::parse(
+            b"REGEDIT4\r\n\r\n[HKEY_CURRENT_USER\\Test]\r\n\"Multi\"=hex(7):61,62,00,63,00,00\r\n",
+        )
+        .unwrap();
+        assert_eq!(reg_file.version(), RegFileVersion::Regedit4);
+        assert_eq!(
+            value(&reg_file, "HKEY_CURRENT_USER\\Test", "Multi"),
+            RegValue::MultiSz(vec!["ab".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn decodes_utf16_files() {
+        let text = format!(
+            "{}[HKEY_CURRENT_USER\\Tëst]\r\n\"Name\"=\"välue\"\r\n",
+            HEADER
+        );
+        let data: Vec<u8> = UTF16LE_BOM
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let reg_file = RegFile::parse(&data).unwrap();
+        assert_eq!(reg_file.version(), RegFileVersion::Regedit5);
+        assert_eq!(
+            value(&reg_file, "HKEY_CURRENT_USER\\Tëst", "Name"),
+            RegValue::Sz("välue".to_string())
+        );
+    }
+}
@@ -0,0 +1,644 @@
+use crate::guid::{Guid, GUID_SIZE};
+use crate::hive::{ensure_remaining, HiveParseError, HiveStructure};
+use bytes::Buf;
+use std::fmt;
+
+const SECURITY_DESCRIPTOR_HEADER_SIZE: usize = 20;
+const SID_HEADER_SIZE: usize = 8;
+const SID_AUTHORITY_SIZE: usize = 6;
+const ACL_HEADER_SIZE: usize = 8;
+const ACE_HEADER_SIZE: usize = 4;
+const ACCESS_MASK_SIZE: usize = 4;
+const OBJECT_FLAGS_SIZE: usize = 4;
+
+// Security descriptor control flags
+const SE_DACL_PRESENT: u16 = 0x0004;
+const SE_SACL_PRESENT: u16 = 0x0010;
+const SE_DACL_AUTO_INHERIT_REQ: u16 = 0x0100;
+const SE_SACL_AUTO_INHERIT_REQ: u16 = 0x0200;
+const SE_DACL_AUTO_INHERITED: u16 = 0x0400;
+const SE_SACL_AUTO_INHERITED: u16 = 0x0800;
+const SE_DACL_PROTECTED: u16 = 0x1000;
+const SE_SACL_PROTECTED: u16 = 0x2000;
+
+// Object ACE flags, telling which of the GUIDs are present
+const ACE_OBJECT_TYPE_PRESENT: u32 = 0x1;
+const ACE_INHERITED_OBJECT_TYPE_PRESENT: u32 = 0x2;
+
+const ACE_FLAG_ALIASES: [(u8, &str); 7] = [
+    (0x01, "OI"),
+    (0x02, "CI"),
+    (0x04, "NP"),
+    (0x08, "IO"),
+    (0x10, "ID"),
+    (0x40, "SA"),
+    (0x80, "FA"),
+];
+
+// Access rights which have an alias of their own, checked before the individual bits
+const ACCESS_MASK_ALIASES: [(u32, &str); 3] = [(0xF003F, "KA"), (0x20019, "KR"), (0x20006, "KW")];
+
+const ACCESS_RIGHT_ALIASES: [(u32, &str); 17] = [
+    (0x1000_0000, "GA"),
+    (0x8000_0000, "GR"),
+    (0x4000_0000, "GW"),
+    (0x2000_0000, "GX"),
+    (0x0001_0000, "SD"),
+    (0x0002_0000, "RC"),
+    (0x0004_0000, "WD"),
+    (0x0008_0000, "WO"),
+    (0x0000_0001, "CC"),
+    (0x0000_0002, "DC"),
+    (0x0000_0004, "LC"),
+    (0x0000_0008, "SW"),
+    (0x0000_0010, "RP"),
+    (0x0000_0020, "WP"),
+    (0x0000_0040, "DT"),
+    (0x0000_0080, "LO"),
+    (0x0000_0100, "CR"),
+];
+
+const SID_ALIASES: [(&str, &str); 32] = [
+    ("S-1-1-0", "WD"),
+    ("S-1-3-0", "CO"),
+    ("S-1-3-1", "CG"),
+    ("S-1-3-4", "OW"),
+    ("S-1-5-2", "NU"),
+    ("S-1-5-4", "IU"),
+    ("S-1-5-6", "SU"),
+    ("S-1-5-7", "AN"),
+    ("S-1-5-9", "ED"),
+    ("S-1-5-10", "PS"),
+    ("S-1-5-11", "AU"),
+    ("S-1-5-12", "RC"),
+    ("S-1-5-18", "SY"),
+    ("S-1-5-19", "LS"),
+    ("S-1-5-20", "NS"),
+    ("S-1-5-32-544", "BA"),
+    ("S-1-5-32-545", "BU"),
+    ("S-1-5-32-546", "BG"),
+    ("S-1-5-32-547", "PU"),
+    ("S-1-5-32-548", "AO"),
+    ("S-1-5-32-549", "SO"),
+    ("S-1-5-32-550", "PO"),
+    ("S-1-5-32-551", "BO"),
+    ("S-1-5-32-552", "RE"),
+    ("S-1-5-32-554", "RU"),
+    ("S-1-5-32-555", "RD"),
+    ("S-1-5-32-556", "NO"),
+    ("S-1-15-2-1", "AC"),
+    ("S-1-16-4096", "LW"),
+    ("S-1-16-8192", "ME"),
+    ("S-1-16-12288", "HI"),
+    ("S-1-16-16384", "SI"),
+];
+
+/// A self-relative security descriptor, as stored in sk cells.
+/// Format specification: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-dtyp/7d4dac05-9cef-4563-a058-f108abecce1d
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityDescriptor {
+    revision: u8,
+    control: u16,
+    owner: Option<Sid>,
+    group: Option<Sid>,
+    sacl: Option<Acl>,
+    dacl: Option<Acl>,
+}
+
+/// A security identifier such as S-1-5-32-544
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sid {
+    revision: u8,
+    identifier_authority: u64,
+    sub_authorities: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    revision: u8,
+    aces: Vec<Ace>,
+}
+
+/// An access control entry. The object types are only present in object ACEs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ace {
+    ace_type: AceType,
+    flags: u8,
+    access_mask: u32,
+    object_type: Option<Guid>,
+    inherited_object_type: Option<Guid>,
+    trustee: Sid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AceType {
+    AccessAllowed,
+    AccessDenied,
+    SystemAudit,
+    SystemAlarm,
+    AccessAllowedObject,
+    AccessDeniedObject,
+    SystemAuditObject,
+    SystemAlarmObject,
+    AccessAllowedCallback,
+    AccessDeniedCallback,
+    AccessAllowedCallbackObject,
+    AccessDeniedCallbackObject,
+    SystemAuditCallback,
+    SystemAlarmCallback,
+    SystemAuditCallbackObject,
+    SystemAlarmCallbackObject,
+    SystemMandatoryLabel,
+    SystemResourceAttribute,
+    SystemScopedPolicyId,
+    SystemProcessTrustLabel,
+    Unknown(u8),
+}
+
+impl SecurityDescriptor {
+    /// Parses a self-relative security descriptor. The offset is the file offset of the
+    /// descriptor and is only used for error reporting.
+    pub fn parse(data: &[u8], offset: u64) -> Result<Self, HiveParseError> {
+        let mut buf = data;
+        ensure_remaining(
+            &buf,
+            SECURITY_DESCRIPTOR_HEADER_SIZE,
+            HiveStructure::SecurityDescriptor,
+            offset,
+        )?;
+        let revision = buf.get_u8();
+        // Skip the reserved byte
+        buf.advance(1);
+        let control = buf.get_u16_le();
+        let owner_offset = buf.get_u32_le() as usize;
+        let group_offset = buf.get_u32_le() as usize;
+        let sacl_offset = buf.get_u32_le() as usize;
+        let dacl_offset = buf.get_u32_le() as usize;
+
+        let owner = optional_at(data, owner_offset, offset, Sid::parse)?;
+        let group = optional_at(data, group_offset, offset, Sid::parse)?;
+        let sacl = if control & SE_SACL_PRESENT != 0 {
+            optional_at(data, sacl_offset, offset, Acl::parse)?
+        } else {
+            None
+        };
+        let dacl = if control & SE_DACL_PRESENT != 0 {
+            optional_at(data, dacl_offset, offset, Acl::parse)?
+        } else {
+            None
+        };
+
+        Ok(SecurityDescriptor {
+            revision,
+            control,
+            owner,
+            group,
+            sacl,
+            dacl,
+        })
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    pub fn control(&self) -> u16 {
+        self.control
+    }
+
+    pub fn owner(&self) -> Option<&Sid> {
+        self.owner.as_ref()
+    }
+
+    pub fn group(&self) -> Option<&Sid> {
+        self.group.as_ref()
+    }
+
+    pub fn sacl(&self) -> Option<&Acl> {
+        self.sacl.as_ref()
+    }
+
+    /// Returns the DACL, or `None` when there is no DACL or a NULL DACL granting everyone full
+    /// access, which can be told apart through [`SecurityDescriptor::control`]
+    pub fn dacl(&self) -> Option<&Acl> {
+        self.dacl.as_ref()
+    }
+
+    /// Renders the security descriptor in the Security Descriptor Definition Language, e.g.
+    /// `O:BAG:SYD:(A;CI;KA;;;SY)(A;CI;KR;;;BU)`
+    pub fn sddl(&self) -> String {
+        let mut sddl = String::new();
+        if let Some(owner) = &self.owner {
+            sddl.push_str(&format!("O:{}", owner.sddl()));
+        }
+        if let Some(group) = &self.group {
+            sddl.push_str(&format!("G:{}", group.sddl()));
+        }
+        if self.control & SE_DACL_PRESENT != 0 {
+            sddl.push_str("D:");
+            sddl.push_str(&acl_sddl(
+                self.dacl.as_ref(),
+                self.control,
+                [
+                    (SE_DACL_PROTECTED, "P"),
+                    (SE_DACL_AUTO_INHERIT_REQ, "AR"),
+                    (SE_DACL_AUTO_INHERITED, "AI"),
+                ],
+            ));
+        }
+        if self.control & SE_SACL_PRESENT != 0 {
+            sddl.push_str("S:");
+            sddl.push_str(&acl_sddl(
+                self.sacl.as_ref(),
+                self.control,
+                [
+                    (SE_SACL_PROTECTED, "P"),
+                    (SE_SACL_AUTO_INHERIT_REQ, "AR"),
+                    (SE_SACL_AUTO_INHERITED, "AI"),
+                ],
+            ));
+        }
+        sddl
+    }
+}
+
+impl fmt::Display for SecurityDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sddl())
+    }
+}
+
+impl Sid {
+    fn parse(data: &[u8], offset: u64) -> Result<Self, HiveParseError> {
+        let mut buf = data;
+        ensure_remaining(
+            &buf,
+            SID_HEADER_SIZE,
+            HiveStructure::SecurityDescriptor,
+            offset,
+        )?;
+        let revision = buf.get_u8();
+        let sub_authority_count = buf.get_u8() as usize;
+        let identifier_authority = buf.get_uint(SID_AUTHORITY_SIZE);
+        ensure_remaining(
+            &buf,
+            sub_authority_count * 4,
+            HiveStructure::SecurityDescriptor,
+            offset,
+        )?;
+        let sub_authorities = (0..sub_authority_count).map(|_| buf.get_u32_le()).collect();
+        Ok(Sid {
+            revision,
+            identifier_authority,
+            sub_authorities,
+        })
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    pub fn identifier_authority(&self) -> u64 {
+        self.identifier_authority
+    }
+
+    pub fn sub_authorities(&self) -> &[u32] {
+        &self.sub_authorities
+    }
+
+    /// Returns the two letter SDDL alias of a well-known SID, e.g. `BA` for the builtin
+    /// administrators
+    pub fn alias(&self) -> Option<&'static str> {
+        let sid = self.to_string();
+        SID_ALIASES
+            .iter()
+            .find(|(known, _)| *known == sid)
+            .map(|(_, alias)| *alias)
+    }
+
+    fn sddl(&self) -> String {
+        self.alias()
+            .map_or_else(|| self.to_string(), |alias| alias.to_owned())
+    }
+}
+
+impl fmt::Display for Sid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "S-{}-", self.revision)?;
+        // Authorities that do not fit in 32 bits are written in hexadecimal
+        if self.identifier_authority > u32::MAX as u64 {
+            write!(f, "{:#014x}", self.identifier_authority)?;
+        } else {
+            write!(f, "{}", self.identifier_authority)?;
+        }
+        for sub_authority in &self.sub_authorities {
+            write!(f, "-{}", sub_authority)?;
+        }
+        Ok(())
+    }
+}
+
+impl Acl {
+    fn parse(data: &[u8], offset: u64) -> Result<Self, HiveParseError> {
+        let mut buf = data;
+        ensure_remaining(
+            &buf,
+            ACL_HEADER_SIZE,
+            HiveStructure::SecurityDescriptor,
+            offset,
+        )?;
+        let revision = buf.get_u8();
+        buf.advance(1);
+        let acl_size = buf.get_u16_le() as usize;
+        let ace_count = buf.get_u16_le();
+        buf.advance(2);
+        ensure_remaining(&data, acl_size, HiveStructure::SecurityDescriptor, offset)?;
+        if acl_size < ACL_HEADER_SIZE {
+            return Err(HiveParseError::Malformed {
+                structure: HiveStructure::SecurityDescriptor,
+                offset,
+                msg: "ACL smaller than its header",
+            });
+        }
+        let mut aces_data = &data[ACL_HEADER_SIZE..acl_size];
+        let mut aces = vec![];
+        for _ in 0..ace_count {
+            let ace_offset = offset + (acl_size - aces_data.len()) as u64;
+            let (ace, ace_size) = Ace::parse(aces_data, ace_offset)?;
+            aces.push(ace);
+            aces_data = &aces_data[ace_size..];
+        }
+        Ok(Acl { revision, aces })
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    pub fn aces(&self) -> &[Ace] {
+        &self.aces
+    }
+}
+
+impl Ace {
+    /// Parses an ACE, returning it together with its size on disk
+    fn parse(data: &[u8], offset: u64) -> Result<(Self, usize), HiveParseError> {
+        let mut buf = data;
+        ensure_remaining(
+            &buf,
+            ACE_HEADER_SIZE + ACCESS_MASK_SIZE,
+            HiveStructure::SecurityDescriptor,
+            offset,
+        )?;
+        let ace_type = AceType::from(buf.get_u8());
+        let flags = buf.get_u8();
+        let ace_size = buf.get_u16_le() as usize;
+        if ace_size < ACE_HEADER_SIZE + ACCESS_MASK_SIZE || ace_size > data.len() {
+            return Err(HiveParseError::Malformed {
+                structure: HiveStructure::SecurityDescriptor,
+                offset,
+                msg: "ACE size outside of the ACL",
+            });
+        }
+        let mut buf = &data[ACE_HEADER_SIZE..ace_size];
+        let access_mask = buf.get_u32_le();
+
+        let mut object_type = None;
+        let mut inherited_object_type = None;
+        if ace_type.is_object() {
+            ensure_remaining(
+                &buf,
+                OBJECT_FLAGS_SIZE,
+                HiveStructure::SecurityDescriptor,
+                offset,
+            )?;
+            let object_flags = buf.get_u32_le();
+            if object_flags & ACE_OBJECT_TYPE_PRESENT != 0 {
+                object_type = Some(read_guid(&mut buf, offset)?);
+            }
+            if object_flags & ACE_INHERITED_OBJECT_TYPE_PRESENT != 0 {
+                inherited_object_type = Some(read_guid(&mut buf, offset)?);
+            }
+        }
+        // Callback and resource attribute ACEs carry additional data after the SID
+        let trustee = Sid::parse(buf, offset)?;
+        Ok((
+            Ace {
+                ace_type,
+                flags,
+                access_mask,
+                object_type,
+                inherited_object_type,
+                trustee,
+            },
+            ace_size,
+        ))
+    }
+
+    pub fn ace_type(&self) -> AceType {
+        self.ace_type
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn access_mask(&self) -> u32 {
+        self.access_mask
+    }
+
+    pub fn object_type(&self) -> Option<&Guid> {
+        self.object_type.as_ref()
+    }
+
+    pub fn inherited_object_type(&self) -> Option<&Guid> {
+        self.inherited_object_type.as_ref()
+    }
+
+    pub fn trustee(&self) -> &Sid {
+        &self.trustee
+    }
+
+    /// Renders the ACE as `(type;flags;rights;object_guid;inherit_object_guid;account_sid)`
+    pub fn sddl(&self) -> String {
+        let flags: String = ACE_FLAG_ALIASES
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, alias)| *alias)
+            .collect();
+        format!(
+            "({};{};{};{};{};{})",
+            self.ace_type.sddl(),
+            flags,
+            access_mask_sddl(self.access_mask),
+            self.object_type
+                .map(|guid| guid.to_string())
+                .unwrap_or_default(),
+            self.inherited_object_type
+                .map(|guid| guid.to_string())
+                .unwrap_or_default(),
+            self.trustee.sddl()
+        )
+    }
+}
+
+impl AceType {
+    /// Object ACEs carry object type GUIDs between the access mask and the SID
+    pub fn is_object(&self) -> bool {
+        matches!(
+            self,
+            AceType::AccessAllowedObject
+                | AceType::AccessDeniedObject
+                | AceType::SystemAuditObject
+                | AceType::SystemAlarmObject
+                | AceType::AccessAllowedCallbackObject
+                | AceType::AccessDeniedCallbackObject
+                | AceType::SystemAuditCallbackObject
+                | AceType::SystemAlarmCallbackObject
+        )
+    }
+
+    /// Returns the SDDL ace type string, or the type in hexadecimal when SDDL has none for it
+    pub fn sddl(&self) -> String {
+        let alias = match self {
+            AceType::AccessAllowed => "A",
+            AceType::AccessDenied => "D",
+            AceType::SystemAudit => "AU",
+            AceType::SystemAlarm => "AL",
+            AceType::AccessAllowedObject => "OA",
+            AceType::AccessDeniedObject => "OD",
+            AceType::SystemAuditObject => "OU",
+            AceType::SystemAlarmObject => "OL",
+            AceType::AccessAllowedCallback => "XA",
+            AceType::AccessDeniedCallback => "XD",
+            AceType::AccessAllowedCallbackObject => "ZA",
+            AceType::SystemAuditCallback => "XU",
+            AceType::SystemMandatoryLabel => "ML",
+            AceType::SystemResourceAttribute => "RA",
+            AceType::SystemScopedPolicyId => "SP",
+            AceType::SystemProcessTrustLabel => "TL",
+            other => return format!("{:#04x}", u8::from(*other)),
+        };
+        alias.to_owned()
+    }
+}
+
+impl From<u8> for AceType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => AceType::AccessAllowed,
+            0x01 => AceType::AccessDenied,
+            0x02 => AceType::SystemAudit,
+            0x03 => AceType::SystemAlarm,
+            0x05 => AceType::AccessAllowedObject,
+            0x06 => AceType::AccessDeniedObject,
+            0x07 => AceType::SystemAuditObject,
+            0x08 => AceType::SystemAlarmObject,
+            0x09 => AceType::AccessAllowedCallback,
+            0x0A => AceType::AccessDeniedCallback,
+            0x0B => AceType::AccessAllowedCallbackObject,
+            0x0C => AceType::AccessDeniedCallbackObject,
+            0x0D => AceType::SystemAuditCallback,
+            0x0E => AceType::SystemAlarmCallback,
+            0x0F => AceType::SystemAuditCallbackObject,
+            0x10 => AceType::SystemAlarmCallbackObject,
+            0x11 => AceType::SystemMandatoryLabel,
+            0x12 => AceType::SystemResourceAttribute,
+            0x13 => AceType::SystemScopedPolicyId,
+            0x14 => AceType::SystemProcessTrustLabel,
+            unknown => AceType::Unknown(unknown),
+        }
+    }
+}
+
+impl From<AceType> for u8 {
+    fn from(value: AceType) -> Self {
+        match value {
+            AceType::AccessAllowed => 0x00,
+            AceType::AccessDenied => 0x01,
+            AceType::SystemAudit => 0x02,
+            AceType::SystemAlarm => 0x03,
+            AceType::AccessAllowedObject => 0x05,
+            AceType::AccessDeniedObject => 0x06,
+            AceType::SystemAuditObject => 0x07,
+            AceType::SystemAlarmObject => 0x08,
+            AceType::AccessAllowedCallback => 0x09,
+            AceType::AccessDeniedCallback => 0x0A,
+            AceType::AccessAllowedCallbackObject => 0x0B,
+            AceType::AccessDeniedCallbackObject => 0x0C,
+            AceType::SystemAuditCallback => 0x0D,
+            AceType::SystemAlarmCallback => 0x0E,
+            AceType::SystemAuditCallbackObject => 0x0F,
+            AceType::SystemAlarmCallbackObject => 0x10,
+            AceType::SystemMandatoryLabel => 0x11,
+            AceType::SystemResourceAttribute => 0x12,
+            AceType::SystemScopedPolicyId => 0x13,
+            AceType::SystemProcessTrustLabel => 0x14,
+            AceType::Unknown(value) => value,
+        }
+    }
+}
+
+/// Parses the structure at the given offset inside the descriptor, an offset of 0 meaning the
+/// structure is absent
+fn optional_at<T>(
+    data: &[u8],
+    relative_offset: usize,
+    offset: u64,
+    parse: fn(&[u8], u64) -> Result<T, HiveParseError>,
+) -> Result<Option<T>, HiveParseError> {
+    if relative_offset == 0 {
+        return Ok(None);
+    }
+    if relative_offset >= data.len() {
+        return Err(HiveParseError::Truncated {
+            structure: HiveStructure::SecurityDescriptor,
+            offset,
+            needed: relative_offset + 1,
+            available: data.len(),
+        });
+    }
+    parse(&data[relative_offset..], offset + relative_offset as u64).map(Some)
+}
+
+fn read_guid(buf: &mut &[u8], offset: u64) -> Result<Guid, HiveParseError> {
+    ensure_remaining(buf, GUID_SIZE, HiveStructure::SecurityDescriptor, offset)?;
+    let mut bytes = [0; GUID_SIZE];
+    buf.copy_to_slice(&mut bytes);
+    Ok(Guid::from_bytes(bytes))
+}
+
+fn acl_sddl(acl: Option<&Acl>, control: u16, flag_aliases: [(u16, &str); 3]) -> String {
+    let mut sddl: String = flag_aliases
+        .iter()
+        .filter(|(flag, _)| control & flag != 0)
+        .map(|(_, alias)| *alias)
+        .collect();
+    match acl {
+        Some(acl) => acl.aces.iter().for_each(|ace| sddl.push_str(&ace.sddl())),
+        None => sddl.push_str("NO_ACCESS_CONTROL"),
+    }
+    sddl
+}
+
+/// Renders an access mask with its SDDL aliases, falling back to hexadecimal when some of the
+/// bits have no alias
+fn access_mask_sddl(access_mask: u32) -> String {
+    if let Some((_, alias)) = ACCESS_MASK_ALIASES
+        .iter()
+        .find(|(mask, _)| *mask == access_mask)
+    {
+        return alias.to_string();
+    }
+    let known = ACCESS_RIGHT_ALIASES
+        .iter()
+        .fold(0, |known, (right, _)| known | right);
+    if access_mask & !known != 0 {
+        return format!("{:#x}", access_mask);
+    }
+    ACCESS_RIGHT_ALIASES
+        .iter()
+        .filter(|(right, _)| access_mask & right != 0)
+        .map(|(_, alias)| *alias)
+        .collect()
+}
@@ -0,0 +1,123 @@
+use bytes::{Bytes, BytesMut};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+const PAGE_SIZE: usize = 4096;
+pub(crate) const DEFAULT_CACHED_PAGES: usize = 64;
+
+/// A source of hive data which can be read on demand
+pub(crate) trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Reads a stream in fixed size pages, keeping the most recently used pages in memory
+pub(crate) struct PageCache {
+    reader: Box<dyn ReadSeek>,
+    // Offset in the stream where the cached data starts
+    base_offset: u64,
+    len: usize,
+    capacity: usize,
+    pages: HashMap<usize, Bytes>,
+    // Page numbers from least to most recently used
+    recently_used: VecDeque<usize>,
+}
+
+impl PageCache {
+    /// Creates a cache over the part of the stream starting at `base_offset`, which extends to
+    /// the end of the stream
+    pub(crate) fn new(
+        mut reader: Box<dyn ReadSeek>,
+        base_offset: u64,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        let len = usize::try_from(stream_len.saturating_sub(base_offset)).unwrap_or(usize::MAX);
+        Ok(PageCache {
+            reader,
+            base_offset,
+            len,
+            capacity: capacity.max(1),
+            pages: HashMap::new(),
+            recently_used: VecDeque::new(),
+        })
+    }
+
+    /// Returns the number of bytes readable through the cache
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Reads `len` bytes starting at `start`. Reads within a single page do not copy.
+    pub(crate) fn read(&mut self, start: usize, len: usize) -> io::Result<Option<Bytes>> {
+        let Some(end) = start.checked_add(len).filter(|end| *end <= self.len) else {
+            return Ok(None);
+        };
+        if len == 0 {
+            return Ok(Some(Bytes::new()));
+        }
+        let first_page = start / PAGE_SIZE;
+        let last_page = (end - 1) / PAGE_SIZE;
+        if first_page == last_page {
+            let page = self.page(first_page)?;
+            let page_start = first_page * PAGE_SIZE;
+            return Ok(Some(page.slice(start - page_start..end - page_start)));
+        }
+        let mut data = BytesMut::with_capacity(len);
+        for page_number in first_page..=last_page {
+            let page = self.page(page_number)?;
+            let page_start = page_number * PAGE_SIZE;
+            let from = start.max(page_start) - page_start;
+            let to = end.min(page_start + page.len()) - page_start;
+            data.extend_from_slice(&page[from..to]);
+        }
+        Ok(Some(data.freeze()))
+    }
+
+    fn page(&mut self, page_number: usize) -> io::Result<Bytes> {
+        if let Some(page) = self.pages.get(&page_number) {
+            let page = page.clone();
+            self.touch(page_number);
+            return Ok(page);
+        }
+
+        let page_start = page_number * PAGE_SIZE;
+        let page_len = PAGE_SIZE.min(self.len - page_start);
+        let mut page = vec![0; page_len];
+        self.reader
+            .seek(SeekFrom::Start(self.base_offset + page_start as u64))?;
+        self.reader.read_exact(&mut page)?;
+        let page = Bytes::from(page);
+
+        if self.pages.len() >= self.capacity {
+            if let Some(evicted) = self.recently_used.pop_front() {
+                self.pages.remove(&evicted);
+            }
+        }
+        self.pages.insert(page_number, page.clone());
+        self.recently_used.push_back(page_number);
+        Ok(page)
+    }
+
+    fn touch(&mut self, page_number: usize) {
+        if let Some(position) = self
+            .recently_used
+            .iter()
+            .position(|used| *used == page_number)
+        {
+            self.recently_used.remove(position);
+        }
+        self.recently_used.push_back(page_number);
+    }
+}
+
+impl fmt::Debug for PageCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageCache")
+            .field("base_offset", &self.base_offset)
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .field("cached_pages", &self.pages.len())
+            .finish()
+    }
+}
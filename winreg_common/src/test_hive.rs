@@ -0,0 +1,269 @@
+//! Builds small hive files in memory, for tests which need a hive without shipping one
+
+use crate::hive::{HiveBaseBlock, HIVE_BASE_BLOCK_SIZE};
+use bytes::Bytes;
+
+const HIVE_BIN_SIZE: usize = 4096;
+const HIVE_BIN_HEADER_SIZE: usize = 32;
+const CELL_ALIGNMENT: usize = 8;
+pub const CELL_OFFSET_NONE: u32 = 0xFFFF_FFFF;
+const KEY_HIVE_ENTRY: u16 = 0x0004;
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_RESIDENT_FLAG: u32 = 0x8000_0000;
+const DATA_RESIDENT_MAX_SIZE: usize = 4;
+const CHECKSUM_OFFSET: usize = 508;
+
+/// The last written timestamp of every key built, 2023-01-01T00:00:00Z
+pub const LAST_WRITTEN: u64 = 133_170_048_000_000_000;
+
+/// A key to write into a hive, with its values and subkeys
+#[derive(Debug, Clone)]
+pub struct TestKey {
+    name: String,
+    values: Vec<(String, u32, Vec<u8>)>,
+    subkeys: Vec<TestKey>,
+}
+
+impl TestKey {
+    pub fn new(name: &str) -> Self {
+        TestKey {
+            name: name.to_string(),
+            values: vec![],
+            subkeys: vec![],
+        }
+    }
+
+    pub fn with_value(mut self, name: &str, data_type: u32, data: &[u8]) -> Self {
+        self.values
+            .push((name.to_string(), data_type, data.to_vec()));
+        self
+    }
+
+    pub fn with_subkey(mut self, subkey: TestKey) -> Self {
+        self.subkeys.push(subkey);
+        self
+    }
+}
+
+/// Appends cells to the hive bins data, starting a new bin when a cell does not fit in the
+/// current one
+#[derive(Debug, Default)]
+pub struct HiveBuilder {
+    bins: Vec<u8>,
+    cursor: usize,
+}
+
+impl HiveBuilder {
+    pub fn new() -> Self {
+        HiveBuilder::default()
+    }
+
+    /// Appends an allocated cell holding the payload and returns its offset
+    pub fn allocated_cell(&mut self, payload: &[u8]) -> u32 {
+        let size = (payload.len() + 4).next_multiple_of(CELL_ALIGNMENT);
+        self.cell(-(size as i32), payload, size)
+    }
+
+    /// Appends a free cell of the given size holding the payload and returns its offset
+    pub fn free_cell(&mut self, payload: &[u8], size: usize) -> u32 {
+        assert!(size >= payload.len() + 4 && size.is_multiple_of(CELL_ALIGNMENT));
+        self.cell(size as i32, payload, size)
+    }
+
+    /// Writes the key with its values and subkeys, returning the offset of its nk cell
+    pub fn key(&mut self, key: &TestKey, parent: u32) -> u32 {
+        let flags = if parent == CELL_OFFSET_NONE {
+            KEY_COMP_NAME | KEY_HIVE_ENTRY
+        } else {
+            KEY_COMP_NAME
+        };
+        // The nk cell is written first so its offset can be given to the subkeys as parent
+        let placeholder = named_key(&key.name, flags, parent, (0, CELL_OFFSET_NONE), (0, 0));
+        let offset = self.allocated_cell(&placeholder);
+
+        let subkeys: Vec<u32> = key
+            .subkeys
+            .iter()
+            .map(|subkey| self.key(subkey, offset))
+            .collect();
+        let subkey_list = if subkeys.is_empty() {
+            CELL_OFFSET_NONE
+        } else {
+            let mut list = b"li".to_vec();
+            list.extend((subkeys.len() as u16).to_le_bytes());
+            list.extend(subkeys.iter().flat_map(|subkey| subkey.to_le_bytes()));
+            self.allocated_cell(&list)
+        };
+
+        let values: Vec<u32> = key
+            .values
+            .iter()
+            .map(|(name, data_type, data)| {
+                let (data_size, data_offset) = if data.len() <= DATA_RESIDENT_MAX_SIZE {
+                    let mut resident = [0; DATA_RESIDENT_MAX_SIZE];
+                    resident[..data.len()].copy_from_slice(data);
+                    (
+                        DATA_RESIDENT_FLAG | data.len() as u32,
+                        u32::from_le_bytes(resident),
+                    )
+                } else {
+                    (data.len() as u32, self.allocated_cell(data))
+                };
+                self.allocated_cell(&value_key(name, *data_type, data_size, data_offset))
+            })
+            .collect();
+        let value_list = if values.is_empty() {
+            CELL_OFFSET_NONE
+        } else {
+            let list: Vec<u8> = values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            self.allocated_cell(&list)
+        };
+
+        let payload = named_key(
+            &key.name,
+            flags,
+            parent,
+            (subkeys.len() as u32, subkey_list),
+            (values.len() as u32, value_list),
+        );
+        let start = offset as usize + 4;
+        self.bins[start..start + payload.len()].copy_from_slice(&payload);
+        offset
+    }
+
+    /// Returns the hive file, a clean base block with a valid checksum followed by the bins
+    pub fn finish(mut self, root_offset: u32) -> Bytes {
+        self.close_bin();
+        let mut hive = base_block(root_offset, self.bins.len() as u32, 1, 1);
+        hive.extend(self.bins);
+        Bytes::from(hive)
+    }
+
+    fn cell(&mut self, size_field: i32, payload: &[u8], size: usize) -> u32 {
+        assert!(size <= HIVE_BIN_SIZE - HIVE_BIN_HEADER_SIZE);
+        if self.cursor == self.bins.len() || self.cursor + size > self.bins.len() {
+            self.close_bin();
+            let bin_start = self.bins.len();
+            self.bins.resize(bin_start + HIVE_BIN_SIZE, 0);
+            self.bins[bin_start..bin_start + 4].copy_from_slice(b"hbin");
+            self.bins[bin_start + 4..bin_start + 8]
+                .copy_from_slice(&(bin_start as u32).to_le_bytes());
+            self.bins[bin_start + 8..bin_start + 12]
+                .copy_from_slice(&(HIVE_BIN_SIZE as u32).to_le_bytes());
+            self.cursor = bin_start + HIVE_BIN_HEADER_SIZE;
+        }
+        let offset = self.cursor;
+        self.bins[offset..offset + 4].copy_from_slice(&size_field.to_le_bytes());
+        self.bins[offset + 4..offset + 4 + payload.len()].copy_from_slice(payload);
+        self.cursor += size;
+        offset as u32
+    }
+
+    /// Fills the rest of the current bin with a free cell
+    fn close_bin(&mut self) {
+        let remaining = self.bins.len() - self.cursor;
+        if remaining > 0 {
+            self.bins[self.cursor..self.cursor + 4]
+                .copy_from_slice(&(remaining as i32).to_le_bytes());
+            self.cursor = self.bins.len();
+        }
+    }
+}
+
+/// Builds a hive holding the key as its root key
+pub fn build_hive(root: &TestKey) -> Bytes {
+    let mut builder = HiveBuilder::new();
+    let root_offset = builder.key(root, CELL_OFFSET_NONE);
+    builder.finish(root_offset)
+}
+
+/// Builds a base block with a valid checksum
+pub fn base_block(
+    root_offset: u32,
+    hive_bins_data_size: u32,
+    primary_sequence_number: u32,
+    secondary_sequence_number: u32,
+) -> Vec<u8> {
+    let mut block = vec![0; HIVE_BASE_BLOCK_SIZE];
+    block[..4].copy_from_slice(b"regf");
+    let fields = [
+        primary_sequence_number,
+        secondary_sequence_number,
+        LAST_WRITTEN as u32,
+        (LAST_WRITTEN >> 32) as u32,
+        1,
+        5,
+        0,
+        1,
+        root_offset,
+        hive_bins_data_size,
+        1,
+    ];
+    for (index, field) in fields.iter().enumerate() {
+        block[4 + index * 4..8 + index * 4].copy_from_slice(&field.to_le_bytes());
+    }
+    set_checksum(&mut block);
+    block
+}
+
+/// Recalculates the checksum of a base block after changing it
+pub fn set_checksum(block: &mut [u8]) {
+    let checksum = HiveBaseBlock::calculate_checksum(block);
+    block[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Builds the payload of an nk cell with a compressed name. Subkeys and values are given as
+/// their count and list offset.
+pub fn named_key(
+    name: &str,
+    flags: u16,
+    parent: u32,
+    subkeys: (u32, u32),
+    values: (u32, u32),
+) -> Vec<u8> {
+    assert!(name.is_ascii());
+    let mut payload = b"nk".to_vec();
+    payload.extend(flags.to_le_bytes());
+    payload.extend(LAST_WRITTEN.to_le_bytes());
+    for field in [
+        0,
+        parent,
+        subkeys.0,
+        0,
+        subkeys.1,
+        CELL_OFFSET_NONE,
+        values.0,
+        values.1,
+        CELL_OFFSET_NONE,
+        CELL_OFFSET_NONE,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ] {
+        payload.extend(field.to_le_bytes());
+    }
+    payload.extend((name.len() as u16).to_le_bytes());
+    payload.extend(0u16.to_le_bytes());
+    payload.extend(name.as_bytes());
+    payload
+}
+
+/// Builds the payload of a vk cell with a compressed name
+pub fn value_key(name: &str, data_type: u32, data_size: u32, data_offset: u32) -> Vec<u8> {
+    assert!(name.is_ascii());
+    let mut payload = b"vk".to_vec();
+    payload.extend((name.len() as u16).to_le_bytes());
+    payload.extend(data_size.to_le_bytes());
+    payload.extend(data_offset.to_le_bytes());
+    payload.extend(data_type.to_le_bytes());
+    payload.extend(VALUE_COMP_NAME.to_le_bytes());
+    payload.extend(0u16.to_le_bytes());
+    payload.extend(name.as_bytes());
+    payload
+}
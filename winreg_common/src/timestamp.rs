@@ -0,0 +1,271 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// FILETIMEs count 100-nanosecond intervals since 1601-01-01 00:00:00 UTC
+const INTERVALS_PER_SECOND: u64 = 10_000_000;
+const NANOS_PER_INTERVAL: u64 = 100;
+const SECONDS_PER_DAY: u64 = 86_400;
+const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+// Windows rejects FILETIMEs with the high bit set, the maximum is 30828-09-14 02:48:05.4775807
+const MAX_FILETIME: u64 = i64::MAX as u64;
+const MAX_RFC3339_YEAR: i64 = 9999;
+// Days from 0000-03-01 to 1601-01-01 in the proleptic Gregorian calendar
+const DAYS_TO_1601: i64 = 584_694;
+const FRACTION_DIGITS: usize = 7;
+
+/// A Windows FILETIME, as used for all timestamps in hive files.
+/// A zero value means the timestamp was never set, values above `i64::MAX` are out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileTime {
+    value: u64,
+}
+
+impl FileTime {
+    pub fn new(value: u64) -> Self {
+        FileTime { value }
+    }
+
+    /// Returns the raw number of 100-nanosecond intervals since 1601-01-01
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    /// Returns true when the timestamp is set and within the range Windows accepts
+    pub fn is_valid(&self) -> bool {
+        self.value != 0 && self.value <= MAX_FILETIME
+    }
+
+    /// Converts the timestamp, returning `None` when it is zero, out of range or can not be
+    /// represented by the platform's `SystemTime`
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        if !self.is_valid() {
+            return None;
+        }
+        if self.value >= UNIX_EPOCH_INTERVALS {
+            UNIX_EPOCH.checked_add(intervals_to_duration(self.value - UNIX_EPOCH_INTERVALS))
+        } else {
+            UNIX_EPOCH.checked_sub(intervals_to_duration(UNIX_EPOCH_INTERVALS - self.value))
+        }
+    }
+
+    /// Formats the timestamp as an RFC 3339 UTC string with the full 100-nanosecond precision,
+    /// e.g. `2023-01-02T03:04:05.1234567Z`. Returns `None` when it is zero, out of range or after
+    /// the year 9999, which RFC 3339 can not represent.
+    pub fn to_rfc3339(&self) -> Option<String> {
+        if !self.is_valid() {
+            return None;
+        }
+        let seconds = self.value / INTERVALS_PER_SECOND;
+        let fraction = self.value % INTERVALS_PER_SECOND;
+        let days = (seconds / SECONDS_PER_DAY) as i64;
+        let second_of_day = seconds % SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days + DAYS_TO_1601);
+        if year > MAX_RFC3339_YEAR {
+            return None;
+        }
+        Some(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:0width$}Z",
+            year,
+            month,
+            day,
+            second_of_day / 3600,
+            second_of_day / 60 % 60,
+            second_of_day % 60,
+            fraction,
+            width = FRACTION_DIGITS
+        ))
+    }
+
+    /// Parses an RFC 3339 UTC timestamp such as `2023-01-02T03:04:05.1234567Z`, also accepting
+    /// a plain date, a space instead of the `T`, and omitting the fraction or the `Z`
+    pub fn parse_rfc3339(timestamp: &str) -> Option<FileTime> {
+        let timestamp = timestamp.trim();
+        let timestamp = timestamp.strip_suffix(['Z', 'z']).unwrap_or(timestamp);
+        let (date, time) = match timestamp.split_once(['T', 't', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (timestamp, None),
+        };
+
+        let mut date_fields = date.splitn(3, '-');
+        let year: i64 = parse_field(date_fields.next()?, 4)?;
+        let month: u32 = parse_field(date_fields.next()?, 2)?;
+        let day: u32 = parse_field(date_fields.next()?, 2)?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let (mut seconds_of_day, mut fraction) = (0, 0);
+        if let Some(time) = time {
+            let (time, fraction_digits) = match time.split_once('.') {
+                Some((time, fraction_digits)) => (time, Some(fraction_digits)),
+                None => (time, None),
+            };
+            let mut time_fields = time.splitn(3, ':');
+            let hour: u64 = parse_field(time_fields.next()?, 2)?;
+            let minute: u64 = parse_field(time_fields.next()?, 2)?;
+            let second: u64 = parse_field(time_fields.next()?, 2)?;
+            if hour > 23 || minute > 59 || second > 59 {
+                return None;
+            }
+            seconds_of_day = hour * 3600 + minute * 60 + second;
+            if let Some(digits) = fraction_digits {
+                if digits.is_empty()
+                    || digits.len() > FRACTION_DIGITS
+                    || !digits.bytes().all(|digit| digit.is_ascii_digit())
+                {
+                    return None;
+                }
+                fraction = format!("{:0<width$}", digits, width = FRACTION_DIGITS)
+                    .parse()
+                    .ok()?;
+            }
+        }
+
+        let days = u64::try_from(days_from_civil(year, month, day) - DAYS_TO_1601).ok()?;
+        let value = (days * SECONDS_PER_DAY + seconds_of_day)
+            .checked_mul(INTERVALS_PER_SECOND)?
+            .checked_add(fraction)?;
+        Some(FileTime::new(value)).filter(FileTime::is_valid)
+    }
+}
+
+impl From<u64> for FileTime {
+    fn from(value: u64) -> Self {
+        FileTime::new(value)
+    }
+}
+
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_rfc3339() {
+            Some(rfc3339) => write!(f, "{}", rfc3339),
+            None if self.is_zero() => write!(f, "(not set)"),
+            None => write!(f, "(out of range: {:#018x})", self.value),
+        }
+    }
+}
+
+fn intervals_to_duration(intervals: u64) -> Duration {
+    Duration::new(
+        intervals / INTERVALS_PER_SECOND,
+        ((intervals % INTERVALS_PER_SECOND) * NANOS_PER_INTERVAL) as u32,
+    )
+}
+
+/// Converts a number of days since 0000-03-01 into a (year, month, day) date.
+/// Algorithm from https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) date into the number of days since 0000-03-01, the inverse of
+/// `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses a date or time field consisting of exactly `digits` decimal digits
+fn parse_field<T: std::str::FromStr>(field: &str, digits: usize) -> Option<T> {
+    if field.len() != digits || !field.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_the_unix_epoch() {
+        let epoch = FileTime::new(UNIX_EPOCH_INTERVALS);
+        assert_eq!(epoch.to_system_time(), Some(UNIX_EPOCH));
+        assert_eq!(epoch.to_string(), "1970-01-01T00:00:00.0000000Z");
+        assert_eq!(FileTime::parse_rfc3339("1970-01-01T00:00:00Z"), Some(epoch));
+    }
+
+    #[test]
+    fn converts_the_start_of_1601() {
+        // The first interval is the earliest timestamp, zero means it was never set
+        let first = FileTime::new(1);
+        assert_eq!(first.to_string(), "1601-01-01T00:00:00.0000001Z");
+        assert_eq!(
+            FileTime::parse_rfc3339("1601-01-01T00:00:00.0000001Z"),
+            Some(first)
+        );
+        assert_eq!(FileTime::parse_rfc3339("1601-01-01T00:00:00Z"), None);
+        assert_eq!(FileTime::new(0).to_string(), "(not set)");
+    }
+
+    #[test]
+    fn round_trips_rfc3339() {
+        for timestamp in [
+            "2023-01-02T03:04:05.1234567Z",
+            "2000-02-29T23:59:59.9999999Z",
+            "1900-03-01T00:00:00.0000000Z",
+            "9999-12-31T23:59:59.9999999Z",
+        ] {
+            let filetime = FileTime::parse_rfc3339(timestamp).unwrap();
+            assert_eq!(filetime.to_rfc3339().as_deref(), Some(timestamp));
+        }
+        assert_eq!(
+            FileTime::parse_rfc3339("2023-01-02 03:04:05.5"),
+            FileTime::parse_rfc3339("2023-01-02T03:04:05.5000000Z")
+        );
+        for invalid in [
+            "2023-02-29",
+            "2023-01-02T24:00:00Z",
+            "2023-01-02T03:04:05.12345678Z",
+        ] {
+            assert_eq!(FileTime::parse_rfc3339(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn displays_out_of_range_values() {
+        let after_9999 = FileTime::parse_rfc3339("9999-12-31T23:59:59.9999999Z")
+            .unwrap()
+            .value()
+            + 1;
+        assert_eq!(FileTime::new(after_9999).to_rfc3339(), None);
+        assert_eq!(
+            FileTime::new(after_9999).to_string(),
+            format!("(out of range: {:#018x})", after_9999)
+        );
+        let high_bit = FileTime::new(MAX_FILETIME + 1);
+        assert_eq!(high_bit.to_system_time(), None);
+        assert_eq!(high_bit.to_string(), "(out of range: 0x8000000000000000)");
+    }
+}
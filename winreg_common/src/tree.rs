@@ -0,0 +1,267 @@
+use crate::hive::{names_equal, HiveParseError, Key, RegValue, Value, MAX_KEY_DEPTH};
+use crate::timestamp::FileTime;
+use bytes::Bytes;
+use std::collections::HashSet;
+
+const KEY_PATH_SEPARATOR: char = '\\';
+
+/// A registry key held in memory with its values and subkeys, independent of where it was
+/// read from. Names are compared case-insensitively, as in the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyNode {
+    name: String,
+    // Zero when unknown, e.g. for keys read from .reg files
+    last_written_timestamp: FileTime,
+    class_name: Option<String>,
+    values: Vec<ValueNode>,
+    subkeys: Vec<KeyNode>,
+}
+
+/// A registry value held in memory. The default value of a key has an empty name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueNode {
+    name: String,
+    data_type: u32,
+    data: Bytes,
+}
+
+impl KeyNode {
+    pub fn new(name: impl Into<String>) -> Self {
+        KeyNode {
+            name: name.into(),
+            last_written_timestamp: FileTime::default(),
+            class_name: None,
+            values: vec![],
+            subkeys: vec![],
+        }
+    }
+
+    /// Reads a key of a hive with all of its values and subkeys into memory
+    pub fn from_key(key: &Key) -> Result<Self, HiveParseError> {
+        let mut visited = HashSet::from([key.offset()]);
+        KeyNode::read_key(key, &mut visited, 0)
+    }
+
+    fn read_key(
+        key: &Key,
+        visited: &mut HashSet<u32>,
+        depth: usize,
+    ) -> Result<Self, HiveParseError> {
+        let values = key
+            .values()?
+            .iter()
+            .map(ValueNode::from_value)
+            .collect::<Result<_, _>>()?;
+        let mut subkeys = vec![];
+        // Subkeys forming cycles or nested deeper than any valid hive allows are left out
+        if depth < MAX_KEY_DEPTH {
+            for subkey in key.subkeys()? {
+                if visited.insert(subkey.offset()) {
+                    subkeys.push(KeyNode::read_key(&subkey, visited, depth + 1)?);
+                }
+            }
+        }
+        Ok(KeyNode {
+            name: key.name().into_owned(),
+            last_written_timestamp: key.last_written_timestamp(),
+            class_name: key.class_name()?,
+            values,
+            subkeys,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn last_written_timestamp(&self) -> FileTime {
+        self.last_written_timestamp
+    }
+
+    pub fn class_name(&self) -> Option<&str> {
+        self.class_name.as_deref()
+    }
+
+    pub fn values(&self) -> &[ValueNode] {
+        &self.values
+    }
+
+    pub fn subkeys(&self) -> &[KeyNode] {
+        &self.subkeys
+    }
+
+    /// Returns the value with the given name, use an empty name for the default value
+    pub fn value(&self, name: &str) -> Option<&ValueNode> {
+        self.values
+            .iter()
+            .find(|value| names_equal(&value.name, name))
+    }
+
+    pub fn subkey(&self, name: &str) -> Option<&KeyNode> {
+        self.subkeys
+            .iter()
+            .find(|subkey| names_equal(&subkey.name, name))
+    }
+
+    /// Returns the key at the given path relative to this key
+    pub fn open_key(&self, path: &str) -> Option<&KeyNode> {
+        split_path(path).try_fold(self, |key, name| key.subkey(name))
+    }
+
+    /// Adds a value, replacing any value with the same name
+    pub fn set_value(&mut self, value: ValueNode) {
+        match self
+            .values
+            .iter_mut()
+            .find(|existing| names_equal(&existing.name, &value.name))
+        {
+            Some(existing) => *existing = value,
+            None => self.values.push(value),
+        }
+    }
+
+    pub fn remove_value(&mut self, name: &str) -> Option<ValueNode> {
+        let position = self
+            .values
+            .iter()
+            .position(|value| names_equal(&value.name, name))?;
+        Some(self.values.remove(position))
+    }
+
+    /// Returns the key at the given path relative to this key, creating any missing keys
+    pub fn create_key(&mut self, path: &str) -> &mut KeyNode {
+        split_path(path).fold(self, |key, name| {
+            let position = match key
+                .subkeys
+                .iter()
+                .position(|subkey| names_equal(&subkey.name, name))
+            {
+                Some(position) => position,
+                None => {
+                    key.subkeys.push(KeyNode::new(name));
+                    key.subkeys.len() - 1
+                }
+            };
+            &mut key.subkeys[position]
+        })
+    }
+
+    /// Removes the key at the given path relative to this key, along with its subkeys
+    pub fn remove_key(&mut self, path: &str) -> Option<KeyNode> {
+        let names: Vec<&str> = split_path(path).collect();
+        let (name, parent_names) = names.split_last()?;
+        let mut parent = self;
+        for parent_name in parent_names {
+            parent = parent
+                .subkeys
+                .iter_mut()
+                .find(|subkey| names_equal(&subkey.name, parent_name))?;
+        }
+        let position = parent
+            .subkeys
+            .iter()
+            .position(|subkey| names_equal(&subkey.name, name))?;
+        Some(parent.subkeys.remove(position))
+    }
+}
+
+impl ValueNode {
+    pub fn new(name: impl Into<String>, data_type: u32, data: Bytes) -> Self {
+        ValueNode {
+            name: name.into(),
+            data_type,
+            data,
+        }
+    }
+
+    /// Reads a value of a hive with its data into memory
+    pub fn from_value(value: &Value) -> Result<Self, HiveParseError> {
+        // The default value is stored with an empty name
+        let name = if value.value_key().raw_value_name().is_empty() {
+            String::new()
+        } else {
+            value.name().into_owned()
+        };
+        Ok(ValueNode::new(name, value.data_type(), value.data()?))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name.is_empty()
+    }
+
+    pub fn data_type(&self) -> u32 {
+        self.data_type
+    }
+
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Returns the value data decoded according to its data type
+    pub fn reg_value(&self) -> RegValue {
+        RegValue::decode(self.data_type, self.data.clone())
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split(KEY_PATH_SEPARATOR)
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{parse_registry, Hive, REG_DWORD, REG_SZ};
+    use crate::regfile::RegFile;
+    use crate::test_hive::{build_hive, TestKey, LAST_WRITTEN};
+
+    #[test]
+    fn reads_hive_keys_like_reg_files() {
+        let hostname: Vec<u8> = "box\0"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        let root = TestKey::new("ROOT").with_subkey(
+            TestKey::new("Tcpip")
+                .with_value("Hostname", REG_SZ, &hostname)
+                .with_value("", REG_DWORD, &7u32.to_le_bytes())
+                .with_subkey(TestKey::new("Parameters")),
+        );
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let hive = Hive::new(parse_registry(build_hive(&root), &pool).unwrap());
+        let from_hive = KeyNode::from_key(&hive.open_key("Tcpip").unwrap().unwrap()).unwrap();
+        assert_eq!(
+            from_hive.last_written_timestamp(),
+            FileTime::new(LAST_WRITTEN)
+        );
+        assert_eq!(from_hive.class_name(), None);
+
+        let reg_file = RegFile::parse_str(
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\SYSTEM\\Tcpip]\r\n\
+             \"Hostname\"=\"box\"\r\n\
+             @=dword:00000007\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\SYSTEM\\Tcpip\\Parameters]\r\n",
+        )
+        .unwrap();
+        let from_reg_file = reg_file
+            .open_key("HKEY_LOCAL_MACHINE\\SYSTEM\\Tcpip")
+            .unwrap();
+        assert_eq!(from_hive.name(), from_reg_file.name());
+        assert_eq!(from_hive.values(), from_reg_file.values());
+        assert_eq!(from_hive.subkeys()[0].name(), "Parameters");
+        assert_eq!(
+            from_hive.subkeys()[0].values(),
+            from_reg_file.subkeys()[0].values()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{csv, file_name, json, reg, relative_key_path, ExportError, ExportFormat};
+use crate::{full_key_path, split_key_path, KEY_PATH_SEPARATOR};
+use rayon::ThreadPool;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use winreg_common::hive::{Hive, ParseOptions};
+use winreg_common::kind::HiveKind;
+use winreg_common::root::RootKey;
+
+/// Exports registry keys with their subkeys to files in an output directory
+pub trait ExportBackend {
+    /// Exports the key at the path, e.g. `HKLM\\SOFTWARE\\Microsoft`, to the output directory
+    fn export_key(&mut self, key_path: &str, output_dir: &Path) -> Result<(), ExportError>;
+}
+
+/// Exports keys from the live registry with `reg save`, writing a hive file per key. Only
+/// available on Windows.
+#[derive(Debug)]
+pub struct RegSaveBackend {}
+
+impl RegSaveBackend {
+    pub fn new() -> Result<Self, ExportError> {
+        if !cfg!(target_os = "windows") {
+            return Err(ExportError {
+                msg: "Exporting from the live registry is only supported on Windows".to_string(),
+            });
+        }
+        Ok(RegSaveBackend {})
+    }
+}
+
+impl ExportBackend for RegSaveBackend {
+    fn export_key(&mut self, key_path: &str, output_dir: &Path) -> Result<(), ExportError> {
+        let key_names = split_key_path(key_path);
+        match key_names.first() {
+            Some(root) if RootKey::try_from(root.as_str()).is_ok() => {}
+            _ => {
+                return Err(ExportError {
+                    msg: format!("The key {} does not start with a root key", key_path),
+                })
+            }
+        }
+        let reg_key = key_names.join("\\");
+        let output_file = output_dir.join(format!("{}.dat", file_name(&reg_key)));
+
+        let output = Command::new("reg")
+            .args([
+                OsStr::new("save"),
+                OsStr::new(&reg_key),
+                output_file.as_os_str(),
+            ])
+            .output()
+            .map_err(|err| ExportError {
+                msg: err.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(ExportError {
+                msg: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Exports keys from a hive file without accessing the live registry, so this works on any
+/// platform. Keys are given either as full paths like `HKLM\\SOFTWARE\\Microsoft`, which must be
+/// below the mount point, or relative to the root key of the hive, where an empty path exports
+/// the whole hive.
+pub struct OfflineBackend {
+    hive: Hive,
+    mount_point: String,
+    format: ExportFormat,
+}
+
+impl OfflineBackend {
+    /// Opens the hive file to export keys from, parsing it on the given thread pool. The mount
+    /// point names the root key in the output and is detected from the kind of hive when not
+    /// given.
+    pub fn open(
+        input_hive: &Path,
+        pool: &ThreadPool,
+        options: &ParseOptions,
+        mount_point: Option<&str>,
+        format: ExportFormat,
+    ) -> Result<Self, ExportError> {
+        let hive =
+            Hive::open_with_options(input_hive, pool, options).map_err(|err| ExportError {
+                msg: format!("Failed opening {}: {}", input_hive.display(), err),
+            })?;
+        let mount_point = match mount_point {
+            Some(mount_point) => mount_point.to_string(),
+            None => HiveKind::detect(&hive)
+                .mount_point()
+                .map(str::to_string)
+                .ok_or_else(|| ExportError {
+                    msg: format!(
+                        "The mount point of {} could not be detected and must be given",
+                        input_hive.display()
+                    ),
+                })?,
+        };
+        Ok(OfflineBackend::new(hive, &mount_point, format))
+    }
+
+    /// Exports keys from a hive which is already parsed, e.g. one read from a stream, loaded at
+    /// the mount point
+    pub fn new(hive: Hive, mount_point: &str, format: ExportFormat) -> Self {
+        OfflineBackend {
+            hive,
+            mount_point: mount_point.trim_matches(KEY_PATH_SEPARATOR).to_string(),
+            format,
+        }
+    }
+
+    pub fn hive(&self) -> &Hive {
+        &self.hive
+    }
+
+    pub fn mount_point(&self) -> &str {
+        &self.mount_point
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+}
+
+impl ExportBackend for OfflineBackend {
+    fn export_key(&mut self, key_path: &str, output_dir: &Path) -> Result<(), ExportError> {
+        let relative_path = relative_key_path(key_path, &self.mount_point)?;
+        let key = self
+            .hive
+            .open_key(&relative_path)
+            .map_err(|err| ExportError {
+                msg: err.to_string(),
+            })?
+            .ok_or_else(|| ExportError {
+                msg: format!("The key {} does not exist in the hive", relative_path),
+            })?;
+        let full_path = full_key_path(&self.mount_point, &relative_path);
+        let output_file = output_dir.join(format!(
+            "{}.{}",
+            file_name(&full_path),
+            self.format.extension()
+        ));
+        match self.format {
+            ExportFormat::Reg => reg::export_reg_file(&key, &self.mount_point, &output_file),
+            ExportFormat::Json => json::export_json_file(&key, &self.mount_point, &output_file),
+            ExportFormat::Ndjson => json::export_ndjson_file(&key, &self.mount_point, &output_file),
+            ExportFormat::Csv => csv::export_csv_file(&key, &self.mount_point, &output_file),
+        }
+    }
+}
+
+/// Records the keys it is asked to export without writing anything, for checking which keys an
+/// export covers. Keys added with `with_failing_key` fail to export.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingBackend {
+    exported_keys: Vec<(String, PathBuf)>,
+    failing_keys: Vec<String>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        RecordingBackend::default()
+    }
+
+    pub fn with_failing_key(mut self, key_path: &str) -> Self {
+        self.failing_keys.push(key_path.to_string());
+        self
+    }
+
+    /// Returns the paths of the exported keys along with the output directories, in the order
+    /// they were exported
+    pub fn exported_keys(&self) -> &[(String, PathBuf)] {
+        &self.exported_keys
+    }
+}
+
+impl ExportBackend for RecordingBackend {
+    fn export_key(&mut self, key_path: &str, output_dir: &Path) -> Result<(), ExportError> {
+        if self.failing_keys.iter().any(|key| key == key_path) {
+            return Err(ExportError {
+                msg: format!("Failed exporting {}", key_path),
+            });
+        }
+        self.exported_keys
+            .push((key_path.to_string(), output_dir.to_path_buf()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+    use serde_json::json;
+    use std::fs;
+    use winreg_common::hive::{parse_registry, REG_BINARY, REG_DWORD, REG_SZ};
+    use winreg_common::test_hive::{build_hive, TestKey};
+
+    fn utf16(string: &str) -> Vec<u8> {
+        string
+            .encode_utf16()
+            .chain([0])
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    }
+
+    fn test_hive() -> Hive {
+        let root = TestKey::new("ROOT").with_subkey(
+            TestKey::new("Tcpip")
+                .with_value("Hostname", REG_SZ, &utf16("box"))
+                .with_value("", REG_DWORD, &7u32.to_le_bytes())
+                .with_subkey(TestKey::new("Parameters").with_value(
+                    "Data",
+                    REG_BINARY,
+                    &[1, 2, 3, 4, 5],
+                )),
+        );
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        Hive::new(parse_registry(build_hive(&root), &pool).unwrap())
+    }
+
+    /// Exports the Tcpip key of the test hive in the format and returns the file written
+    fn export_tcpip(format: ExportFormat) -> Vec<u8> {
+        let output_dir = std::env::temp_dir().join(format!(
+            "winreg_export_{}_{}",
+            std::process::id(),
+            format.extension()
+        ));
+        fs::create_dir_all(&output_dir).unwrap();
+        let mut backend = OfflineBackend::new(test_hive(), "HKLM\\SYSTEM\\", format);
+        backend
+            .export_key("HKEY_LOCAL_MACHINE\\SYSTEM\\Tcpip", &output_dir)
+            .unwrap();
+        let output_file = output_dir.join(format!("HKLM-SYSTEM-Tcpip.{}", format.extension()));
+        let output = fs::read(&output_file);
+        fs::remove_dir_all(&output_dir).unwrap();
+        output.unwrap()
+    }
+
+    #[test]
+    fn exports_reg_files() {
+        let output = export_tcpip(ExportFormat::Reg);
+        assert_eq!(output[..2], [0xFF, 0xFE]);
+        let units: Vec<u16> = output[2..]
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        assert_eq!(
+            String::from_utf16(&units).unwrap(),
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKLM\\SYSTEM\\Tcpip]\r\n\
+             \"Hostname\"=\"box\"\r\n\
+             @=dword:00000007\r\n\
+             \r\n\
+             [HKLM\\SYSTEM\\Tcpip\\Parameters]\r\n\
+             \"Data\"=hex:01,02,03,04,05\r\n\
+             \r\n"
+        );
+    }
+
+    #[test]
+    fn exports_json_files() {
+        let output: serde_json::Value =
+            serde_json::from_slice(&export_tcpip(ExportFormat::Json)).unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "path": "HKLM\\SYSTEM\\Tcpip",
+                "name": "Tcpip",
+                "last_written": "2023-01-01T00:00:00.0000000Z",
+                "class_name": null,
+                "values": [
+                    {"name": "Hostname", "type": "REG_SZ", "size": 8, "data": "box"},
+                    {"name": "", "type": "REG_DWORD", "size": 4, "data": 7}
+                ],
+                "subkeys": [{
+                    "path": "HKLM\\SYSTEM\\Tcpip\\Parameters",
+                    "name": "Parameters",
+                    "last_written": "2023-01-01T00:00:00.0000000Z",
+                    "class_name": null,
+                    "values": [
+                        {"name": "Data", "type": "REG_BINARY", "size": 5, "data": "0102030405"}
+                    ],
+                    "subkeys": []
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn exports_ndjson_files() {
+        let output = String::from_utf8(export_tcpip(ExportFormat::Ndjson)).unwrap();
+        let records: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                json!({
+                    "path": "HKLM\\SYSTEM\\Tcpip",
+                    "name": "Tcpip",
+                    "last_written": "2023-01-01T00:00:00.0000000Z",
+                    "class_name": null,
+                    "values": [
+                        {"name": "Hostname", "type": "REG_SZ", "size": 8, "data": "box"},
+                        {"name": "", "type": "REG_DWORD", "size": 4, "data": 7}
+                    ]
+                }),
+                json!({
+                    "path": "HKLM\\SYSTEM\\Tcpip\\Parameters",
+                    "name": "Parameters",
+                    "last_written": "2023-01-01T00:00:00.0000000Z",
+                    "class_name": null,
+                    "values": [
+                        {"name": "Data", "type": "REG_BINARY", "size": 5, "data": "0102030405"}
+                    ]
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_csv_files() {
+        assert_eq!(
+            String::from_utf8(export_tcpip(ExportFormat::Csv)).unwrap(),
+            "key_path,last_written,value_name,type,data,size\n\
+             HKLM\\SYSTEM\\Tcpip,2023-01-01T00:00:00.0000000Z,Hostname,REG_SZ,box,8\n\
+             HKLM\\SYSTEM\\Tcpip,2023-01-01T00:00:00.0000000Z,,REG_DWORD,7,4\n\
+             HKLM\\SYSTEM\\Tcpip\\Parameters,2023-01-01T00:00:00.0000000Z,Data,REG_BINARY,\
+             0102030405,5\n"
+        );
+    }
+
+    #[test]
+    fn rejects_keys_outside_the_mount_point() {
+        let output_dir = std::env::temp_dir();
+        let mut backend = OfflineBackend::new(test_hive(), "HKLM\\SYSTEM", ExportFormat::Csv);
+        let error = backend
+            .export_key("HKLM\\SOFTWARE\\Tcpip", &output_dir)
+            .unwrap_err();
+        assert_eq!(
+            error.msg(),
+            "The key HKLM\\SOFTWARE\\Tcpip is not in the hive loaded at HKLM\\SYSTEM"
+        );
+    }
+}
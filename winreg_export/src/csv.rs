@@ -0,0 +1,145 @@
+use crate::{hex_string, read_values, walk_keys, ExportError};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use winreg_common::hive::{format_data_type, Key, RegValue};
+use winreg_common::tree::ValueNode;
+
+const CSV_HEADER: [&str; 6] = [
+    "key_path",
+    "last_written",
+    "value_name",
+    "type",
+    "data",
+    "size",
+];
+
+/// A value as written to a CSV row, in the order of the header, along with the key it belongs to
+#[derive(Serialize)]
+struct ValueRow<'a> {
+    key_path: &'a str,
+    last_written: &'a str,
+    value_name: &'a str,
+    data_type: String,
+    data: String,
+    size: usize,
+}
+
+/// Writes the values of the key and its subkeys to a CSV file, one row per value
+pub fn export_csv_file(
+    key: &Key,
+    mount_point: &str,
+    output_file: &Path,
+) -> Result<(), ExportError> {
+    let mut file = File::create(output_file).map_err(|err| ExportError {
+        msg: format!("Failed creating {}: {}", output_file.display(), err),
+    })?;
+    write_csv(key, mount_point, &mut file)
+}
+
+/// Writes the values of the key and its subkeys as CSV with a header row. Each row holds the
+/// full key path, the last written timestamp of the key, the value name (empty for the default
+/// value), the type name, the decoded data and the data size in bytes. Strings of a
+/// REG_MULTI_SZ value are separated by line breaks within a quoted field. Keys without values
+/// produce no rows, so a subtree without any values is written as the header alone.
+pub fn write_csv(key: &Key, mount_point: &str, writer: &mut impl Write) -> Result<(), ExportError> {
+    let mut csv_writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    csv_writer
+        .write_record(CSV_HEADER)
+        .map_err(|err| ExportError {
+            msg: err.to_string(),
+        })?;
+    walk_keys(key, mount_point, |key, path| {
+        let last_written = key
+            .last_written_timestamp()
+            .to_rfc3339()
+            .unwrap_or_default();
+        for value in read_values(key, path) {
+            csv_writer
+                .serialize(value_row(&value, path, &last_written))
+                .map_err(|err| ExportError {
+                    msg: err.to_string(),
+                })?;
+        }
+        Ok(())
+    })?;
+    csv_writer.flush().map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
+
+fn value_row<'a>(value: &'a ValueNode, key_path: &'a str, last_written: &'a str) -> ValueRow<'a> {
+    let data = match value.reg_value() {
+        RegValue::Sz(string) | RegValue::ExpandSz(string) | RegValue::Link(string) => string,
+        RegValue::MultiSz(strings) => strings.join("\n"),
+        RegValue::Dword(number) | RegValue::DwordBigEndian(number) => number.to_string(),
+        RegValue::Qword(number) => number.to_string(),
+        RegValue::None(data) | RegValue::Binary(data) | RegValue::Unknown(_, data) => {
+            hex_string(&data)
+        }
+    };
+    ValueRow {
+        key_path,
+        last_written,
+        value_name: value.name(),
+        data_type: format_data_type(value.data_type()),
+        data,
+        size: value.data().len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+    use winreg_common::hive::{parse_registry, Hive, REG_DWORD, REG_SZ};
+    use winreg_common::test_hive::{build_hive, TestKey};
+
+    fn utf16(string: &str) -> Vec<u8> {
+        string
+            .encode_utf16()
+            .chain([0])
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    }
+
+    fn test_hive() -> Hive {
+        let root = TestKey::new("ROOT")
+            .with_subkey(TestKey::new("Empty").with_subkey(TestKey::new("Nested")))
+            .with_subkey(
+                TestKey::new("Tcpip")
+                    .with_value("Hostname", REG_SZ, &utf16("box"))
+                    .with_value("", REG_DWORD, &7u32.to_le_bytes()),
+            );
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        Hive::new(parse_registry(build_hive(&root), &pool).unwrap())
+    }
+
+    fn csv_output(hive: &Hive, path: &str) -> String {
+        let key = hive.open_key(path).unwrap().unwrap();
+        let mut output = vec![];
+        write_csv(&key, "HKLM\\SYSTEM", &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_a_row_per_value() {
+        assert_eq!(
+            csv_output(&test_hive(), "Tcpip"),
+            "key_path,last_written,value_name,type,data,size\n\
+             HKLM\\SYSTEM\\Tcpip,2023-01-01T00:00:00.0000000Z,Hostname,REG_SZ,box,8\n\
+             HKLM\\SYSTEM\\Tcpip,2023-01-01T00:00:00.0000000Z,,REG_DWORD,7,4\n"
+        );
+    }
+
+    #[test]
+    fn writes_the_header_for_a_subtree_without_values() {
+        assert_eq!(
+            csv_output(&test_hive(), "Empty"),
+            "key_path,last_written,value_name,type,data,size\n"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{hex_string, read_values, walk_keys, ExportError, KEY_PATH_SEPARATOR};
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use winreg_common::hive::{format_data_type, Key, RegValue};
+use winreg_common::tree::ValueNode;
+
+/// A key as serialized to JSON. Nested exports include the subkeys, while NDJSON exports
+/// write one record per key without them.
+#[derive(Serialize)]
+struct KeyRecord {
+    path: String,
+    name: String,
+    last_written: Option<String>,
+    class_name: Option<String>,
+    values: Vec<ValueRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subkeys: Option<Vec<KeyRecord>>,
+}
+
+#[derive(Serialize)]
+struct ValueRecord {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    size: usize,
+    data: serde_json::Value,
+}
+
+/// Writes the key and its subkeys as a single JSON document of nested key objects
+pub fn export_json_file(
+    key: &Key,
+    mount_point: &str,
+    output_file: &Path,
+) -> Result<(), ExportError> {
+    let mut writer = create_file(output_file)?;
+    write_json(key, mount_point, &mut writer)?;
+    writer.flush().map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
+
+/// Writes the key and its subkeys as newline delimited JSON, one key object per line
+pub fn export_ndjson_file(
+    key: &Key,
+    mount_point: &str,
+    output_file: &Path,
+) -> Result<(), ExportError> {
+    let mut writer = create_file(output_file)?;
+    write_ndjson(key, mount_point, &mut writer)?;
+    writer.flush().map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
+
+/// Writes the key and its subkeys as nested JSON. Keys whose values or subkeys can not be read
+/// are exported partially, as for NDJSON.
+pub fn write_json(
+    key: &Key,
+    mount_point: &str,
+    writer: &mut impl Write,
+) -> Result<(), ExportError> {
+    // Keys are visited depth first, so the records of a key and its ancestors are kept until
+    // a key outside of them is reached
+    let mut records: Vec<KeyRecord> = vec![];
+    walk_keys(key, mount_point, |key, path| {
+        while records.len() > 1 && !is_subkey_path(path, &records[records.len() - 1].path) {
+            close_record(&mut records);
+        }
+        let mut record = flat_record(key, path.to_string());
+        record.subkeys = Some(vec![]);
+        records.push(record);
+        Ok(())
+    })?;
+    while records.len() > 1 {
+        close_record(&mut records);
+    }
+    serde_json::to_writer_pretty(&mut *writer, &records.pop()).map_err(|err| ExportError {
+        msg: err.to_string(),
+    })?;
+    writeln!(writer).map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
+
+/// Writes the key and its subkeys as newline delimited JSON, streaming one key at a time in
+/// depth first order. Keys whose values or subkeys can not be read are exported partially.
+pub fn write_ndjson(
+    key: &Key,
+    mount_point: &str,
+    writer: &mut impl Write,
+) -> Result<(), ExportError> {
+    walk_keys(key, mount_point, |key, path| {
+        let record = flat_record(key, path.to_string());
+        serde_json::to_writer(&mut *writer, &record).map_err(|err| ExportError {
+            msg: err.to_string(),
+        })?;
+        writeln!(writer).map_err(|err| ExportError {
+            msg: err.to_string(),
+        })
+    })
+}
+
+/// Moves the last record into the subkeys of the record before it
+fn close_record(records: &mut Vec<KeyRecord>) {
+    let Some(record) = records.pop() else {
+        return;
+    };
+    if let Some(subkeys) = records
+        .last_mut()
+        .and_then(|parent| parent.subkeys.as_mut())
+    {
+        subkeys.push(record);
+    }
+}
+
+fn is_subkey_path(path: &str, parent_path: &str) -> bool {
+    path.strip_prefix(parent_path)
+        .is_some_and(|rest| rest.starts_with(KEY_PATH_SEPARATOR))
+}
+
+fn flat_record(key: &Key, path: String) -> KeyRecord {
+    let class_name = key.class_name().unwrap_or_else(|e| {
+        eprintln!("Failed reading class name of {}: {}", path, e);
+        None
+    });
+    let values = read_values(key, &path).iter().map(value_record).collect();
+    KeyRecord {
+        name: key.name().into_owned(),
+        last_written: key.last_written_timestamp().to_rfc3339(),
+        class_name,
+        values,
+        subkeys: None,
+        path,
+    }
+}
+
+/// Serializes a value with its data decoded: strings as JSON strings, REG_MULTI_SZ as an array
+/// of strings, numbers as JSON numbers and anything else as a hex string
+fn value_record(value: &ValueNode) -> ValueRecord {
+    let data = match value.reg_value() {
+        RegValue::Sz(string) | RegValue::ExpandSz(string) | RegValue::Link(string) => {
+            json!(string)
+        }
+        RegValue::MultiSz(strings) => json!(strings),
+        RegValue::Dword(number) | RegValue::DwordBigEndian(number) => json!(number),
+        RegValue::Qword(number) => json!(number),
+        RegValue::None(data) | RegValue::Binary(data) | RegValue::Unknown(_, data) => {
+            json!(hex_string(&data))
+        }
+    };
+    ValueRecord {
+        name: value.name().to_string(),
+        data_type: format_data_type(value.data_type()),
+        size: value.data().len(),
+        data,
+    }
+}
+
+fn create_file(output_file: &Path) -> Result<BufWriter<File>, ExportError> {
+    let file = File::create(output_file).map_err(|err| ExportError {
+        msg: format!("Failed creating {}: {}", output_file.display(), err),
+    })?;
+    Ok(BufWriter::new(file))
+}
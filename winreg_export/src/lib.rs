@@ -1,7 +1,16 @@
+pub mod backend;
+pub mod csv;
+pub mod json;
+pub mod reg;
+
+use backend::ExportBackend;
 use std::collections::HashSet;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::Path;
+use winreg_common::hive::{Key, Value};
 use winreg_common::root::RootKey;
+use winreg_common::tree::ValueNode;
+
+pub(crate) const KEY_PATH_SEPARATOR: char = '\\';
 
 #[derive(Debug, Clone)]
 pub struct ExportError {
@@ -14,52 +23,212 @@ impl ExportError {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct ExportKey {
-    root: RootKey,
-    sub_keys: HashSet<String>,
+/// The file formats keys can be exported to from hive files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A .reg file as written by regedit
+    Reg,
+    /// A JSON document of nested keys
+    Json,
+    /// Newline delimited JSON with one key per line
+    Ndjson,
+    /// Comma-separated values with one row per value
+    Csv,
 }
 
-impl ExportKey {
-    pub fn new(root: RootKey, sub_keys: HashSet<String>) -> Self {
-        ExportKey { root, sub_keys }
+impl ExportFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::Reg => "reg",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
     }
 }
 
-pub fn export(keys: Vec<ExportKey>, output_dir: PathBuf) -> Result<(), ExportError> {
-    if !cfg!(target_os = "windows") {
-        panic!("The export function is only supported on Windows");
-    }
+/// Exports the keys with the backend. Keys are exported once, even when given several times with
+/// different case or root key names. A key which fails to export is reported and skipped, the
+/// remaining keys are still exported and an error counting the failed keys is returned at the end.
+pub fn export(
+    backend: &mut dyn ExportBackend,
+    keys: &[String],
+    output_dir: &Path,
+) -> Result<(), ExportError> {
+    let mut exported = HashSet::new();
+    let mut failed_keys = 0;
     for key in keys {
-        for sub_key in key.sub_keys {
-            export_key(&key.root, sub_key, output_dir.clone())
-                .unwrap_or_else(|e| println!("Failed exporting key. Error: {}", e.msg()));
+        if !exported.insert(split_key_path(key).join("\\").to_uppercase()) {
+            continue;
+        }
+        if let Err(e) = backend.export_key(key, output_dir) {
+            eprintln!("Failed exporting key {}. Error: {}", key, e.msg());
+            failed_keys += 1;
+        }
+    }
+    if failed_keys > 0 {
+        return Err(ExportError {
+            msg: format!(
+                "Failed exporting {} of {} keys",
+                failed_keys,
+                exported.len()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Builds the full path of a key from the mount point of its hive and its path relative to the
+/// root key of the hive
+pub(crate) fn full_key_path(mount_point: &str, path: &str) -> String {
+    if path.is_empty() {
+        mount_point.to_string()
+    } else {
+        format!("{}{}{}", mount_point, KEY_PATH_SEPARATOR, path)
+    }
+}
+
+/// Visits the key and its subkeys in depth first order, passing each with its full path. Subkeys
+/// which can not be read are skipped with a warning.
+pub(crate) fn walk_keys(
+    key: &Key,
+    mount_point: &str,
+    mut visit: impl FnMut(&Key, &str) -> Result<(), ExportError>,
+) -> Result<(), ExportError> {
+    let mut visited = HashSet::new();
+    let mut keys = vec![(key.clone(), full_key_path(mount_point, &key.path()))];
+    while let Some((key, path)) = keys.pop() {
+        // Guard against cycles in the subkey lists of corrupted hives
+        if !visited.insert(key.offset()) {
+            continue;
+        }
+        visit(&key, &path)?;
+        match key.subkeys() {
+            Ok(subkeys) => keys.extend(subkeys.into_iter().rev().map(|subkey| {
+                let subkey_path = format!("{}{}{}", path, KEY_PATH_SEPARATOR, subkey.name());
+                (subkey, subkey_path)
+            })),
+            Err(e) => eprintln!("Failed reading subkeys of {}: {}", path, e),
         }
     }
     Ok(())
 }
 
-fn export_key(root: &RootKey, sub_key: String, mut output_dir: PathBuf) -> Result<(), ExportError> {
-    let reg_key = format!("{}\\{}", root.get_name(), sub_key);
+/// Returns the values of a key which can be read, printing a warning for the others
+pub(crate) fn key_values<'a>(key: &Key<'a>, path: &str) -> Vec<Value<'a>> {
+    let (values, errors) = key.readable_values();
+    for e in errors {
+        eprintln!("Failed reading values of {}: {}", path, e);
+    }
+    values
+}
 
-    output_dir.push(format!("{}-{}.dat", root.get_name(), sub_key));
+/// Reads the values of a key with their data, skipping values which can not be read with a
+/// warning
+pub(crate) fn read_values(key: &Key, path: &str) -> Vec<ValueNode> {
+    key_values(key, path)
+        .iter()
+        .filter_map(|value| match ValueNode::from_value(value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("Failed reading value {} of {}: {}", value.name(), path, e);
+                None
+            }
+        })
+        .collect()
+}
 
-    let command = format!(
-        "reg save {} {}",
-        reg_key,
-        output_dir.as_path().to_str().unwrap()
-    );
+/// Formats data as lowercase hex digits, two per byte without separators
+pub(crate) fn hex_string(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
 
-    let output = Command::new("cmd")
-        .args(["/C", &command])
-        .output()
-        .map_err(|err| ExportError {
-            msg: err.to_string(),
-        })?;
-    if !output.status.success() {
+/// Resolves a key path to a path relative to the root key of a hive loaded at the mount point
+pub(crate) fn relative_key_path(key: &str, mount_point: &str) -> Result<String, ExportError> {
+    let key = key.replace("\\\\", "\\");
+    let key_names = split_key_path(&key);
+    let Some(first) = key_names.first() else {
+        return Ok(String::new());
+    };
+    if RootKey::try_from(first.as_str()).is_err() {
+        return Ok(key_names.join("\\"));
+    }
+    let mount_point_names = split_key_path(mount_point);
+    let is_below_mount_point = key_names.len() >= mount_point_names.len()
+        && key_names
+            .iter()
+            .zip(&mount_point_names)
+            .all(|(name, mount_point_name)| name.to_uppercase() == mount_point_name.to_uppercase());
+    if !is_below_mount_point {
         return Err(ExportError {
-            msg: String::from_utf8(output.stderr).unwrap(),
+            msg: format!(
+                "The key {} is not in the hive loaded at {}",
+                key, mount_point
+            ),
         });
     }
-    Ok(())
+    Ok(key_names[mount_point_names.len()..].join("\\"))
+}
+
+/// Splits a key path into its names, replacing a short root key name by its full name
+pub(crate) fn split_key_path(path: &str) -> Vec<String> {
+    path.split(KEY_PATH_SEPARATOR)
+        .filter(|name| !name.is_empty())
+        .enumerate()
+        .map(|(index, name)| match RootKey::try_from(name) {
+            Ok(root) if index == 0 => root.get_name().to_string(),
+            _ => name.to_string(),
+        })
+        .collect()
+}
+
+/// Builds a file name from a key path, replacing separators and characters Windows does not
+/// allow in file names
+pub(crate) fn file_name(key_path: &str) -> String {
+    key_path
+        .chars()
+        .map(|c| match c {
+            KEY_PATH_SEPARATOR => '-',
+            '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::RecordingBackend;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn export_merges_short_and_full_root_key_names() {
+        let mut backend = RecordingBackend::new();
+        let keys = keys(&["HKLM\\SOFTWARE", "HKEY_LOCAL_MACHINE\\software"]);
+        export(&mut backend, &keys, Path::new("out")).unwrap();
+        let exported: Vec<&str> = backend
+            .exported_keys()
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(exported, ["HKLM\\SOFTWARE"]);
+    }
+
+    #[test]
+    fn export_continues_after_a_failing_key() {
+        let mut backend = RecordingBackend::new().with_failing_key("HKLM\\SYSTEM");
+        let keys = keys(&["HKLM\\SOFTWARE", "HKLM\\SYSTEM", "HKCU\\Software"]);
+        let error = export(&mut backend, &keys, Path::new("out")).unwrap_err();
+        assert_eq!(error.msg(), "Failed exporting 1 of 3 keys");
+        let exported: Vec<&str> = backend
+            .exported_keys()
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(exported, ["HKLM\\SOFTWARE", "HKCU\\Software"]);
+    }
 }
@@ -0,0 +1,160 @@
+use crate::{key_values, walk_keys, ExportError};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use winreg_common::hive::{Key, Value, REG_BINARY, REG_DWORD, REG_SZ};
+
+const REG_FILE_HEADER: &str = "Windows Registry Editor Version 5.00";
+const NEWLINE: &str = "\r\n";
+const BYTE_ORDER_MARK: [u8; 2] = [0xFF, 0xFE];
+const DEFAULT_VALUE_NAME: &str = "@";
+// regedit wraps hex data once a line reaches this many characters, continuing with a
+// backslash and two spaces of indentation on the next line
+const MAX_HEX_CHARS: usize = 77;
+const HEX_CONTINUATION: &str = "\\\r\n  ";
+const HEX_CONTINUATION_INDENT: usize = 2;
+
+/// Writes the key and its subkeys to a .reg file in the format of regedit's exports
+pub fn export_reg_file(
+    key: &Key,
+    mount_point: &str,
+    output_file: &Path,
+) -> Result<(), ExportError> {
+    let file = File::create(output_file).map_err(|err| ExportError {
+        msg: format!("Failed creating {}: {}", output_file.display(), err),
+    })?;
+    let mut writer = BufWriter::new(file);
+    write_reg_file(key, mount_point, &mut writer)?;
+    writer.flush().map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
+
+/// Writes the key and its subkeys as a "Windows Registry Editor Version 5.00" file, encoded in
+/// UTF-16LE with a byte order mark and CRLF line endings. The root key of the hive is named
+/// by the mount point, e.g. `HKEY_LOCAL_MACHINE\\SOFTWARE`. Keys whose values or subkeys can
+/// not be read are exported partially.
+pub fn write_reg_file(
+    key: &Key,
+    mount_point: &str,
+    writer: &mut impl Write,
+) -> Result<(), ExportError> {
+    writer
+        .write_all(&BYTE_ORDER_MARK)
+        .map_err(|err| ExportError {
+            msg: err.to_string(),
+        })?;
+    write_utf16(writer, &format!("{}{}", REG_FILE_HEADER, NEWLINE))?;
+
+    walk_keys(key, mount_point, |key, path| {
+        write_utf16(writer, &format_key(key, path))
+    })?;
+    write_utf16(writer, NEWLINE)
+}
+
+fn format_key(key: &Key, path: &str) -> String {
+    let mut output = format!("{}[{}]{}", NEWLINE, path, NEWLINE);
+    for value in key_values(key, path) {
+        match format_value(&value) {
+            Ok(line) => {
+                output.push_str(&line);
+                output.push_str(NEWLINE);
+            }
+            Err(e) => eprintln!(
+                "Failed reading value {} of {}: {}",
+                value.name(),
+                path,
+                e.msg()
+            ),
+        }
+    }
+    output
+}
+
+/// Formats a value as a `"name"=data` line, following the encodings regedit uses
+fn format_value(value: &Value) -> Result<String, ExportError> {
+    let data = value
+        .data()
+        .map_err(|e| ExportError { msg: e.to_string() })?;
+    let mut line = if value.value_key().raw_value_name().is_empty() {
+        DEFAULT_VALUE_NAME.to_string()
+    } else {
+        format!("\"{}\"", escape_string(&value.name()))
+    };
+    line.push('=');
+    let line_len = utf16_len(&line);
+
+    match value.data_type() {
+        REG_SZ => match string_data(&data) {
+            Some(string) => line.push_str(&format!("\"{}\"", escape_string(&string))),
+            None => line.push_str(&format_hex_data(REG_SZ, &data, line_len)),
+        },
+        REG_DWORD if data.len() == 4 => line.push_str(&format!(
+            "dword:{:08x}",
+            u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+        )),
+        data_type => line.push_str(&format_hex_data(data_type, &data, line_len)),
+    }
+    Ok(line)
+}
+
+/// Decodes REG_SZ data when it can be written as a quoted string and read back unchanged,
+/// which excludes data with embedded nulls or line breaks and invalid UTF-16
+fn string_data(data: &[u8]) -> Option<String> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    if units.last() == Some(&0) {
+        units.pop();
+    }
+    let string = String::from_utf16(&units).ok()?;
+    if string.contains(['\0', '\r', '\n']) {
+        return None;
+    }
+    Some(string)
+}
+
+/// Formats data as comma-separated hex bytes, prefixed by `hex:` for REG_BINARY and
+/// `hex(type):` for other types. Lines are wrapped the way regedit does, counting from the
+/// length of the value name already written on the line.
+fn format_hex_data(data_type: u32, data: &[u8], line_len: usize) -> String {
+    let mut output = if data_type == REG_BINARY {
+        "hex:".to_string()
+    } else {
+        format!("hex({:x}):", data_type)
+    };
+    let mut line_len = line_len + output.len();
+    for (index, byte) in data.iter().enumerate() {
+        output.push_str(&format!("{:02x}", byte));
+        if index == data.len() - 1 {
+            break;
+        }
+        output.push(',');
+        line_len += 3;
+        if line_len >= MAX_HEX_CHARS {
+            output.push_str(HEX_CONTINUATION);
+            line_len = HEX_CONTINUATION_INDENT;
+        }
+    }
+    output
+}
+
+fn escape_string(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Returns the length in UTF-16 code units, which is how regedit measures line lengths
+fn utf16_len(string: &str) -> usize {
+    string.encode_utf16().count()
+}
+
+fn write_utf16(writer: &mut impl Write, string: &str) -> Result<(), ExportError> {
+    let bytes: Vec<u8> = string.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    writer.write_all(&bytes).map_err(|err| ExportError {
+        msg: err.to_string(),
+    })
+}
@@ -0,0 +1,145 @@
+use std::fs::File;
+use std::io::{self, Read};
+use winreg_common::hive::{HiveBaseBlock, HIVE_BASE_BLOCK_SIZE};
+use winreg_common::kind::HiveKind;
+
+const GUID_SIGNATURE: &[u8] = b"rmtm";
+const FLAG_KTM_LOCKED: u32 = 0x1;
+const FLAG_DEFRAGMENTED: u32 = 0x2;
+
+/// Prints the fields of the base block of each hive. Only the base block is read, so this also
+/// works for truncated hives and transaction logs.
+pub fn print_info(input_hives: &[String]) {
+    for input_hive in input_hives {
+        let base_block = match read_base_block(input_hive) {
+            Ok(base_block) => base_block,
+            Err(e) => {
+                eprintln!("Failed reading base block of {}: {}", input_hive, e);
+                continue;
+            }
+        };
+        println!("{}", input_hive);
+        print_base_block(&base_block);
+    }
+}
+
+fn read_base_block(path: &str) -> io::Result<HiveBaseBlock> {
+    let mut block = vec![0; HIVE_BASE_BLOCK_SIZE];
+    File::open(path)?.read_exact(&mut block)?;
+    HiveBaseBlock::build(&mut block.as_slice())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn print_base_block(base_block: &HiveBaseBlock) {
+    println!(
+        "  Sequence numbers:    {} / {} ({})",
+        base_block.primary_sequence_number(),
+        base_block.secondary_sequence_number(),
+        if base_block.is_dirty() {
+            "dirty"
+        } else {
+            "clean"
+        }
+    );
+    println!(
+        "  Last written:        {}",
+        base_block.last_written_timestamp()
+    );
+    println!(
+        "  Version:             {}.{}",
+        base_block.major_version(),
+        base_block.minor_version()
+    );
+    println!(
+        "  File type:           {} ({})",
+        base_block.file_type(),
+        file_type_name(base_block.file_type())
+    );
+    println!(
+        "  File format:         {} ({})",
+        base_block.file_format(),
+        file_format_name(base_block.file_format())
+    );
+    let file_name = base_block.decoded_file_name();
+    println!("  File name:           {}", file_name);
+    println!(
+        "  Hive kind:           {}",
+        HiveKind::from_file_name(&file_name).unwrap_or(HiveKind::Unknown)
+    );
+    println!("  Root key offset:     {:#x}", base_block.root_key_offset());
+    println!(
+        "  Hive bins size:      {} bytes",
+        base_block.hive_bins_data_size()
+    );
+    println!("  Clustering factor:   {}", base_block.clustering_factor());
+    println!(
+        "  Checksum:            {:#010x} ({}, calculated {:#010x})",
+        base_block.checksum(),
+        if base_block.is_checksum_valid() {
+            "valid"
+        } else {
+            "invalid"
+        },
+        base_block.calculated_checksum()
+    );
+    println!("  RmId:                {}", base_block.rm_id());
+    println!("  LogId:               {}", base_block.log_id());
+    println!(
+        "  Flags:               {:#x}{}",
+        base_block.flags(),
+        flag_names(base_block.flags())
+    );
+    println!("  TmId:                {}", base_block.tm_id());
+    println!(
+        "  GUID signature:      {}",
+        guid_signature(base_block.guid_signature())
+    );
+    println!(
+        "  Last reorganized:    {}",
+        base_block.last_reorganized_timestamp()
+    );
+    println!("  Boot type:           {}", base_block.boot_type());
+    println!("  Boot recover:        {}", base_block.boot_recover());
+}
+
+fn file_type_name(file_type: u32) -> &'static str {
+    match file_type {
+        0 => "primary file",
+        1 => "transaction log",
+        2 => "transaction log, new format",
+        6 => "transaction log, Windows 8.1 format",
+        _ => "unknown",
+    }
+}
+
+fn file_format_name(file_format: u32) -> &'static str {
+    match file_format {
+        1 => "direct memory load",
+        _ => "unknown",
+    }
+}
+
+fn flag_names(flags: u32) -> String {
+    let mut names = vec![];
+    if flags & FLAG_KTM_LOCKED != 0 {
+        names.push("KTM locked");
+    }
+    if flags & FLAG_DEFRAGMENTED != 0 {
+        names.push("defragmented");
+    }
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", names.join(", "))
+    }
+}
+
+fn guid_signature(signature: &[u8]) -> String {
+    if signature == GUID_SIGNATURE {
+        "rmtm".to_string()
+    } else if signature.iter().all(|byte| *byte == 0) {
+        "(not set)".to_string()
+    } else {
+        format!("{:02x?} (unexpected)", signature)
+    }
+}
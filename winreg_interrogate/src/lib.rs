@@ -1,25 +1,106 @@
-use bytes::Bytes;
+pub mod info;
+pub mod query;
+
+use query::Query;
 use rayon::ThreadPool;
-use std::fs;
+use std::borrow::Cow;
 use std::path::Path;
-use std::time::Instant;
-use winreg_common::hive::parse_registry;
+use winreg_common::deleted::{recover_deleted, RecoveredValue};
+use winreg_common::hive::{Hive, ParseOptions};
+use winreg_common::kind::HiveKind;
+
+/// Prints the keys and values recovered from the unallocated cells of each hive which match the
+/// query. Recovered keys whose parent is lost are matched by their name instead of their path,
+/// and values no longer referenced by any key only match queries without key predicates.
+pub fn print_deleted(
+    input_hives: &[String],
+    query: &Query,
+    pool: &ThreadPool,
+    options: &ParseOptions,
+) {
+    for input_hive in input_hives {
+        if is_reg_file(input_hive) {
+            eprintln!(
+                "Skipping {}: deleted entries can only be recovered from hives",
+                input_hive
+            );
+            continue;
+        }
+        let hive = match Hive::open_with_options(input_hive, pool, options) {
+            Ok(hive) => hive,
+            Err(e) => {
+                eprintln!("Failed opening {}: {}", input_hive, e);
+                continue;
+            }
+        };
+        print_hive_warnings(input_hive, &hive);
+        println!("{} ({})", input_hive, HiveKind::detect(&hive));
+        let deleted = match recover_deleted(&hive) {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                eprintln!("Failed recovering deleted entries of {}: {}", input_hive, e);
+                continue;
+            }
+        };
+        for key in deleted.keys() {
+            let path = key.path().map_or_else(|| key.name(), Cow::Borrowed);
+            if !query.matches_key_path(&path, key.last_written_timestamp()) {
+                continue;
+            }
+            let values: Vec<&RecoveredValue> = key
+                .values()
+                .iter()
+                .filter(|value| matches_recovered_value(query, value))
+                .collect();
+            if query.has_value_predicates() && values.is_empty() {
+                continue;
+            }
+            println!(
+                "[recovered] {} (last written {})",
+                path,
+                key.last_written_timestamp()
+            );
+            for value in values {
+                print_recovered_value(value);
+            }
+        }
+        if !query.has_key_predicates() {
+            for value in deleted.orphan_values() {
+                if matches_recovered_value(query, value) {
+                    print_recovered_value(value);
+                }
+            }
+        }
+    }
+}
+
+/// Recovered values whose data was overwritten never match predicates on the data
+fn matches_recovered_value(query: &Query, value: &RecoveredValue) -> bool {
+    query.matches_value_name(&value.name(), value.data_type())
+        && (!query.has_data_predicates()
+            || value.data().is_some_and(|data| query.matches_data(data)))
+}
+
+/// Returns true when the input is a .reg file rather than a hive, judged by its extension
+pub(crate) fn is_reg_file(input: &str) -> bool {
+    Path::new(input)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("reg"))
+}
 
-pub fn test() {
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(std::thread::available_parallelism().unwrap().get())
-        .build()
-        .unwrap();
-    for _ in 0..100 {
-        let start = Instant::now();
-        run_parse_registry(&pool);
-        let duration = start.elapsed();
-        println!("Time elapsed in parse_registry(): {:?}", duration);
+/// Prints the problems found while opening a hive which did not prevent parsing it
+pub(crate) fn print_hive_warnings(input_hive: &str, hive: &Hive) {
+    if let Some(error) = hive.checksum_error() {
+        eprintln!("Warning: {}: {}", input_hive, error);
+    }
+    for (log_path, error) in hive.skipped_logs() {
+        eprintln!("Warning: skipped {}: {}", log_path.display(), error);
     }
 }
 
-fn run_parse_registry(pool: &ThreadPool) {
-    let bytes = Bytes::from(fs::read(Path::new("C://Users/Lars/Documents/CreativeWork/Projects/Rust/winreg-util/hive/HKEY_LOCAL_MACHINE-SOFTWARE.dat")).unwrap());
-    let hive_primary_file = parse_registry(bytes, pool).unwrap();
-    println!("Parsed the entire registry");
+fn print_recovered_value(value: &RecoveredValue) {
+    match value.data() {
+        Some(data) => println!("  [recovered] {} = {}", value.name(), data),
+        None => println!("  [recovered] {} = <overwritten>", value.name()),
+    }
 }
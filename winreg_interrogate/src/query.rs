@@ -0,0 +1,396 @@
+use crate::{is_reg_file, print_hive_warnings};
+use rayon::ThreadPool;
+use regex::{Regex, RegexBuilder};
+use winreg_common::hive::{
+    format_data_type, parse_data_type, Hive, Key, ParseOptions, RegValue, Value,
+};
+use winreg_common::kind::HiveKind;
+use winreg_common::pattern::KeyPathPattern;
+use winreg_common::regfile::RegFile;
+use winreg_common::timestamp::FileTime;
+use winreg_common::tree::{KeyNode, ValueNode};
+
+const ROOT_KEY_DISPLAY_NAME: &str = "(root)";
+const DEFAULT_VALUE_DISPLAY_NAME: &str = "(default)";
+const KEY_PATH_SEPARATOR: char = '\\';
+
+#[derive(Debug, Clone)]
+pub struct QueryError {
+    msg: String,
+}
+
+impl QueryError {
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// Predicates selecting keys and values. Keys match when their path and last written time
+/// match, values additionally need to match all value predicates. Key paths are matched by a
+/// glob pattern, value names and data by regular expressions, all ignoring case.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    key_path: Option<KeyPathPattern>,
+    value_name: Option<Regex>,
+    value_types: Vec<u32>,
+    // Stored lowercased, as substrings are compared case-insensitively
+    data_contains: Option<String>,
+    data_regex: Option<Regex>,
+    written_after: Option<FileTime>,
+    written_before: Option<FileTime>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Matches key paths relative to the root key against a glob pattern, e.g.
+    /// `ControlSet*\\Services\\*\\Parameters`
+    pub fn with_key_path(mut self, pattern: &str) -> Result<Self, QueryError> {
+        let pattern = KeyPathPattern::parse(pattern).map_err(|e| QueryError {
+            msg: format!("Invalid key path pattern '{}': {}", pattern, e.msg()),
+        })?;
+        self.key_path = Some(pattern);
+        Ok(self)
+    }
+
+    /// Matches value names, the unnamed default value is named `(default)`
+    pub fn with_value_name(mut self, pattern: &str) -> Result<Self, QueryError> {
+        self.value_name = Some(build_regex(pattern)?);
+        Ok(self)
+    }
+
+    /// Matches values of any of the given types, named like `REG_SZ` or `sz`, or numbered
+    pub fn with_value_types(mut self, types: &[String]) -> Result<Self, QueryError> {
+        self.value_types = types
+            .iter()
+            .map(|name| {
+                parse_data_type(name).ok_or_else(|| QueryError {
+                    msg: format!("Unknown value type '{}'", name),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// Matches values whose data, as printed, contains the given text
+    pub fn with_data_contains(mut self, text: &str) -> Self {
+        self.data_contains = Some(text.to_lowercase());
+        self
+    }
+
+    /// Matches values whose data, as printed, matches the given pattern
+    pub fn with_data_regex(mut self, pattern: &str) -> Result<Self, QueryError> {
+        self.data_regex = Some(build_regex(pattern)?);
+        Ok(self)
+    }
+
+    /// Matches keys last written at or after the given RFC 3339 timestamp or date
+    pub fn with_written_after(mut self, timestamp: &str) -> Result<Self, QueryError> {
+        self.written_after = Some(parse_timestamp(timestamp)?);
+        Ok(self)
+    }
+
+    /// Matches keys last written before the given RFC 3339 timestamp or date
+    pub fn with_written_before(mut self, timestamp: &str) -> Result<Self, QueryError> {
+        self.written_before = Some(parse_timestamp(timestamp)?);
+        Ok(self)
+    }
+
+    pub fn matches_key(&self, key: &Key) -> bool {
+        self.matches_key_path(&key.path(), key.last_written_timestamp())
+    }
+
+    /// Matches a key by its path relative to the root key and its last written time
+    pub fn matches_key_path(&self, path: &str, last_written: FileTime) -> bool {
+        self.key_path
+            .as_ref()
+            .is_none_or(|pattern| pattern.matches(path))
+            && self.written_after.is_none_or(|after| last_written >= after)
+            && self
+                .written_before
+                .is_none_or(|before| last_written < before)
+    }
+
+    /// Returns true when any predicate applies to keys
+    pub fn has_key_predicates(&self) -> bool {
+        self.key_path.is_some() || self.written_after.is_some() || self.written_before.is_some()
+    }
+
+    /// Returns true when any predicate applies to the data of values
+    pub fn has_data_predicates(&self) -> bool {
+        self.data_contains.is_some() || self.data_regex.is_some()
+    }
+
+    /// Returns true when any predicate applies to values, in which case only keys with
+    /// matching values are reported
+    pub fn has_value_predicates(&self) -> bool {
+        self.value_name.is_some()
+            || !self.value_types.is_empty()
+            || self.data_contains.is_some()
+            || self.data_regex.is_some()
+    }
+
+    pub fn matches_value(&self, value: &Value) -> bool {
+        if !self.matches_value_name(&value.name(), value.data_type()) {
+            return false;
+        }
+        if !self.has_data_predicates() {
+            return true;
+        }
+        match value.reg_value() {
+            Ok(data) => self.matches_data(&data),
+            Err(e) => {
+                eprintln!("Failed reading data of value {}: {}", value.name(), e);
+                false
+            }
+        }
+    }
+
+    /// Matches a key of a .reg file by its full path. Keys read from .reg files have no last
+    /// written time, so they never match predicates on it.
+    pub fn matches_reg_file_key(&self, path: &str) -> bool {
+        self.written_after.is_none()
+            && self.written_before.is_none()
+            && self.matches_key_path(path, FileTime::default())
+    }
+
+    /// Matches a value of a .reg file, the default value is named `(default)` as in hives
+    pub fn matches_value_node(&self, value: &ValueNode) -> bool {
+        self.matches_value_name(value_node_name(value), value.data_type())
+            && (!self.has_data_predicates() || self.matches_data(&value.reg_value()))
+    }
+
+    /// Matches a value by its name and data type, ignoring the predicates on its data
+    pub fn matches_value_name(&self, name: &str, data_type: u32) -> bool {
+        self.value_name
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(name))
+            && (self.value_types.is_empty() || self.value_types.contains(&data_type))
+    }
+
+    /// Matches decoded value data, as printed
+    pub fn matches_data(&self, data: &RegValue) -> bool {
+        let data = data.to_string();
+        self.data_contains
+            .as_ref()
+            .is_none_or(|text| data.to_lowercase().contains(text))
+            && self
+                .data_regex
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&data))
+    }
+}
+
+/// Prints the keys and values of each hive matching the query. Inputs with a .reg extension
+/// are parsed as .reg files instead, see [`print_reg_file_matches`].
+pub fn print_matches(
+    input_hives: &[String],
+    query: &Query,
+    pool: &ThreadPool,
+    options: &ParseOptions,
+) {
+    for input_hive in input_hives {
+        if is_reg_file(input_hive) {
+            print_reg_file_matches(input_hive, query);
+            continue;
+        }
+        let hive = match Hive::open_with_options(input_hive, pool, options) {
+            Ok(hive) => hive,
+            Err(e) => {
+                eprintln!("Failed opening {}: {}", input_hive, e);
+                continue;
+            }
+        };
+        print_hive_warnings(input_hive, &hive);
+        println!("{} ({})", input_hive, HiveKind::detect(&hive));
+        // Only the branches which can match the key path pattern are walked
+        let any_key = KeyPathPattern::any();
+        let pattern = query.key_path.as_ref().unwrap_or(&any_key);
+        for key in pattern.matching_keys(&hive) {
+            match key {
+                Ok(key) if query.matches_key(&key) => print_key_matches(&key, query),
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed reading keys of {}: {}", input_hive, e),
+            }
+        }
+    }
+}
+
+/// Prints the keys and values of a .reg file matching the query. The keys are matched by their
+/// full path, e.g. `HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft`, as .reg files are not tied to a hive.
+pub fn print_reg_file_matches(input_file: &str, query: &Query) {
+    let reg_file = match RegFile::open(input_file) {
+        Ok(reg_file) => reg_file,
+        Err(e) => {
+            eprintln!("Failed parsing {}: {}", input_file, e);
+            return;
+        }
+    };
+    println!("{} (.reg file)", input_file);
+    let mut keys: Vec<(&KeyNode, String)> = reg_file
+        .root()
+        .subkeys()
+        .iter()
+        .rev()
+        .map(|key| (key, key.name().to_string()))
+        .collect();
+    while let Some((key, path)) = keys.pop() {
+        if query.matches_reg_file_key(&path) {
+            print_key_node_matches(key, &path, query);
+        }
+        keys.extend(key.subkeys().iter().rev().map(|subkey| {
+            let subkey_path = format!("{}{}{}", path, KEY_PATH_SEPARATOR, subkey.name());
+            (subkey, subkey_path)
+        }));
+    }
+}
+
+fn print_key_node_matches(key: &KeyNode, path: &str, query: &Query) {
+    let values: Vec<&ValueNode> = key
+        .values()
+        .iter()
+        .filter(|value| query.matches_value_node(value))
+        .collect();
+    if query.has_value_predicates() && values.is_empty() {
+        return;
+    }
+    println!("{}", path);
+    for value in values {
+        println!(
+            "  {} ({}) = {}",
+            value_node_name(value),
+            format_data_type(value.data_type()),
+            value.reg_value()
+        );
+    }
+}
+
+fn value_node_name(value: &ValueNode) -> &str {
+    if value.is_default() {
+        DEFAULT_VALUE_DISPLAY_NAME
+    } else {
+        value.name()
+    }
+}
+
+fn print_key_matches(key: &Key, query: &Query) {
+    let (values, errors) = key.readable_values();
+    for e in errors {
+        eprintln!("Failed reading values of {}: {}", display_path(key), e);
+    }
+    let values: Vec<&Value> = values
+        .iter()
+        .filter(|value| query.matches_value(value))
+        .collect();
+    if query.has_value_predicates() && values.is_empty() {
+        return;
+    }
+    println!(
+        "{} (last written {})",
+        display_path(key),
+        key.last_written_timestamp()
+    );
+    for value in values {
+        print_value(value);
+    }
+}
+
+fn print_value(value: &Value) {
+    let data_type = format_data_type(value.data_type());
+    match value.reg_value() {
+        Ok(data) => println!("  {} ({}) = {}", value.name(), data_type, data),
+        Err(e) => println!("  {} ({}) = <unreadable: {}>", value.name(), data_type, e),
+    }
+}
+
+fn display_path(key: &Key) -> String {
+    let path = key.path();
+    if path.is_empty() {
+        ROOT_KEY_DISPLAY_NAME.to_string()
+    } else {
+        path
+    }
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, QueryError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| QueryError {
+            msg: format!("Invalid pattern '{}': {}", pattern, e),
+        })
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<FileTime, QueryError> {
+    FileTime::parse_rfc3339(timestamp).ok_or_else(|| QueryError {
+        msg: format!(
+            "Invalid timestamp '{}', expected e.g. 2023-01-02 or 2023-01-02T03:04:05Z",
+            timestamp
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winreg_common::hive::REG_DWORD;
+
+    fn reg_file() -> RegFile {
+        RegFile::parse_str(
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run]\r\n\
+             \"Updater\"=\"C:\\\\Temp\\\\upd.exe\"\r\n\
+             @=dword:00000010\r\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_reg_file_keys_by_full_path() {
+        let query = Query::new()
+            .with_key_path(r"HKEY_LOCAL_MACHINE\**\Run")
+            .unwrap();
+        assert!(query.matches_reg_file_key(
+            r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Run"
+        ));
+        assert!(!query.matches_reg_file_key(r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft"));
+
+        // Keys of .reg files have no last written time to compare
+        let query = Query::new().with_written_before("2030-01-01").unwrap();
+        assert!(!query.matches_reg_file_key("HKEY_LOCAL_MACHINE"));
+    }
+
+    #[test]
+    fn matches_reg_file_values() {
+        let reg_file = reg_file();
+        let key = reg_file
+            .open_key(r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Run")
+            .unwrap();
+        let matching = |query: Query| -> Vec<String> {
+            key.values()
+                .iter()
+                .filter(|value| query.matches_value_node(value))
+                .map(|value| value_node_name(value).to_string())
+                .collect()
+        };
+        assert_eq!(
+            matching(Query::new().with_data_contains("TEMP")),
+            ["Updater"]
+        );
+        assert_eq!(
+            matching(Query::new().with_value_name("^\\(default\\)$").unwrap()),
+            ["(default)"]
+        );
+        assert_eq!(
+            matching(
+                Query::new()
+                    .with_value_types(&[format_data_type(REG_DWORD)])
+                    .unwrap()
+            ),
+            ["(default)"]
+        );
+    }
+}